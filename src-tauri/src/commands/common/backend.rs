@@ -0,0 +1,460 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use futures_lite::io::{AsyncReadExt, Cursor as AsyncCursor};
+use tauri::AppHandle;
+use tokio::sync::MutexGuard;
+use crate::commands::transfer::progress::ProgressReader;
+use crate::ftp_ops::FtpConnection;
+use crate::models::{ConnectionProtocol, FileItem, RemoteSession};
+use crate::reconnect::is_connection_alive;
+use crate::s3_ops::S3Client;
+use crate::sftp_ops::SftpClient;
+use crate::utils::{format_bytes, parse_ftp_list_line};
+use crate::webdav_ops::WebDavClient;
+use super::{get_or_reconnect_stream, get_or_create_sftp_client};
+
+const ALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The file operations every remote protocol provides. Commands reach a session's client through
+/// [`with_backend`], which also owns the reconnect-and-retry policy.
+#[async_trait]
+pub trait RemoteBackend: Send {
+    /// Whether the underlying connection still works. Protocols without a persistent connection
+    /// always report `true`, so their failures are never retried.
+    async fn is_alive(&mut self) -> bool;
+    async fn list(&mut self, path: &str) -> Result<Vec<FileItem>, String>;
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String>;
+    async fn write(&mut self, path: &str, data: Vec<u8>) -> Result<(), String>;
+    async fn mkdir(&mut self, path: &str) -> Result<(), String>;
+    async fn remove_file(&mut self, path: &str) -> Result<(), String>;
+    /// Removes a directory whose contents have already been deleted.
+    async fn remove_dir(&mut self, path: &str) -> Result<(), String>;
+    async fn rename(&mut self, src: &str, dst: &str) -> Result<(), String>;
+    /// Uploads a single file, emitting `upload-progress` where the protocol can report it.
+    async fn upload(&mut self, local_path: &Path, remote_path: &str, app: &AppHandle) -> Result<(), String>;
+    /// Downloads a single file of `size` bytes, emitting `upload-progress` where the protocol can report it.
+    async fn download(&mut self, remote_path: &str, local_path: &Path, size: u64, app: &AppHandle) -> Result<(), String>;
+
+    /// Looks `path` up in the listing of its parent directory.
+    async fn stat(&mut self, path: &str) -> Result<FileItem, String> {
+        let trimmed = path.trim_end_matches('/');
+        let Some((parent, name)) = trimmed.rsplit_once('/').filter(|(_, name)| !name.is_empty()) else {
+            return Ok(FileItem {
+                name: "/".to_string(),
+                full_path: "/".to_string(),
+                size: 0,
+                modified: None,
+                is_directory: true,
+                readable_size: "".to_string(),
+                readable_modified: "".to_string(),
+                permissions: "drwxr-xr-x".to_string(),
+            });
+        };
+        let parent = if parent.is_empty() { "/" } else { parent };
+        self.list(parent).await?
+            .into_iter()
+            .find(|item| item.name == name)
+            .ok_or_else(|| format!("'{}' does not exist", path))
+    }
+}
+
+/// Runs a blocking client call on the blocking thread pool.
+async fn blocking<C, T, F>(client: &C, f: F) -> Result<T, String>
+where
+    C: Clone + Send + 'static,
+    T: Send + 'static,
+    F: FnOnce(C) -> Result<T, String> + Send + 'static,
+{
+    let client = client.clone();
+    tokio::task::spawn_blocking(move || f(client)).await.map_err(|e| e.to_string())?
+}
+
+fn join_remote(dir: &str, name: &str) -> String {
+    if dir == "/" { format!("/{}", name) } else { format!("{}/{}", dir.trim_end_matches('/'), name) }
+}
+
+fn progress_reader<R>(inner: R, app: &AppHandle, file_name: String, total_size: u64) -> ProgressReader<R> {
+    ProgressReader {
+        inner,
+        app_handle: app.clone(),
+        file_name,
+        total_size,
+        bytes_read: 0,
+        start_time: Instant::now(),
+        last_emit_time: Instant::now(),
+    }
+}
+
+fn file_name_of(path: &str) -> String {
+    Path::new(path).file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+// This trait's `mkdir`, `rename` and friends share their names with `FtpConnection`'s, so those
+// are called by path to make clear they are not recursive.
+#[async_trait]
+impl RemoteBackend for FtpConnection {
+    async fn is_alive(&mut self) -> bool {
+        is_connection_alive(self, ALIVE_TIMEOUT).await
+    }
+
+    async fn list(&mut self, path: &str) -> Result<Vec<FileItem>, String> {
+        let format = self.list_format();
+        let files = self.list_lines(path).await
+            .map_err(|e| format!("Failed to list directory: {}", e))?;
+        Ok(files.iter().filter_map(|line| parse_ftp_list_line(line, format)).filter(|(name, ..)| name != "." && name != "..").map(|(name, size, is_directory, date_str, permissions)| {
+            let size = if is_directory { 0 } else { size };
+            FileItem {
+                full_path: join_remote(path, &name),
+                name,
+                size,
+                modified: None,
+                is_directory,
+                readable_size: if is_directory { "".to_string() } else { format_bytes(size) },
+                readable_modified: date_str,
+                permissions,
+            }
+        }).collect())
+    }
+
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let mut data_stream = self.retr_as_stream(path).await
+            .map_err(|e| format!("FTP retr failed: {}", e))?;
+        let mut buffer = Vec::new();
+        let result = data_stream.read_to_end(&mut buffer).await;
+        let _ = self.finalize_retr_stream(data_stream).await;
+        result.map_err(|e| format!("Failed to read stream: {}", e))?;
+        Ok(buffer)
+    }
+
+    async fn write(&mut self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        self.put_file(path, &mut AsyncCursor::new(data)).await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn mkdir(&mut self, path: &str) -> Result<(), String> {
+        FtpConnection::mkdir(self, path).await.map_err(|e| e.to_string())
+    }
+
+    async fn remove_file(&mut self, path: &str) -> Result<(), String> {
+        FtpConnection::rm(self, path).await.map_err(|e| format!("Failed to delete file {}: {}", path, e))
+    }
+
+    async fn remove_dir(&mut self, path: &str) -> Result<(), String> {
+        FtpConnection::rmdir(self, path).await.map_err(|e| format!("Failed to remove directory {}: {}", path, e))
+    }
+
+    async fn rename(&mut self, src: &str, dst: &str) -> Result<(), String> {
+        FtpConnection::rename(self, src, dst).await.map_err(|e| e.to_string())
+    }
+
+    async fn upload(&mut self, local_path: &Path, remote_path: &str, app: &AppHandle) -> Result<(), String> {
+        let file_content = std::fs::read(local_path).map_err(|e| e.to_string())?;
+        let total_size = file_content.len() as u64;
+        let file_name = local_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let mut reader = progress_reader(AsyncCursor::new(file_content), app, file_name, total_size);
+        self.put_file(remote_path, &mut reader).await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn download(&mut self, remote_path: &str, local_path: &Path, size: u64, app: &AppHandle) -> Result<(), String> {
+        let data_stream = self.retr_as_stream(remote_path).await.map_err(|e| e.to_string())?;
+        let mut reader = progress_reader(data_stream, app, file_name_of(remote_path), size);
+        let mut buffer = Vec::new();
+        let result = reader.read_to_end(&mut buffer).await;
+        let _ = self.finalize_retr_stream(reader.inner).await;
+        result.map_err(|e| e.to_string())?;
+
+        if let Some(parent) = local_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        std::fs::write(local_path, buffer).map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl RemoteBackend for SftpClient {
+    async fn is_alive(&mut self) -> bool {
+        let client = self.clone();
+        tokio::task::spawn_blocking(move || client.is_alive()).await.unwrap_or(false)
+    }
+
+    async fn list(&mut self, path: &str) -> Result<Vec<FileItem>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.list_directory(&path)).await
+    }
+
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| {
+            let temp_file_path = std::env::temp_dir().join(format!("bestftp_read_{}.tmp", uuid::Uuid::new_v4()));
+            let result = c.download_file(&path, &temp_file_path.to_string_lossy())
+                .and_then(|_| std::fs::read(&temp_file_path).map_err(|e| format!("Failed to read temporary file: {}", e)));
+            let _ = std::fs::remove_file(temp_file_path);
+            result
+        }).await
+    }
+
+    async fn write(&mut self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| {
+            if data.is_empty() {
+                return c.create_empty_file(&path);
+            }
+            let temp_file_path = std::env::temp_dir().join(format!("bestftp_write_{}.tmp", uuid::Uuid::new_v4()));
+            std::fs::write(&temp_file_path, data)
+                .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+            let result = c.upload_file(&temp_file_path.to_string_lossy(), &path);
+            let _ = std::fs::remove_file(temp_file_path);
+            result
+        }).await
+    }
+
+    async fn mkdir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.create_directory(&path)).await
+    }
+
+    async fn remove_file(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove_file(&path)).await
+    }
+
+    async fn remove_dir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove_directory(&path)).await
+    }
+
+    async fn rename(&mut self, src: &str, dst: &str) -> Result<(), String> {
+        let (src, dst) = (src.to_string(), dst.to_string());
+        blocking(self, move |c| c.rename_file(&src, &dst)).await
+    }
+
+    async fn upload(&mut self, local_path: &Path, remote_path: &str, _app: &AppHandle) -> Result<(), String> {
+        let (local_path, remote_path) = (local_path.to_string_lossy().to_string(), remote_path.to_string());
+        blocking(self, move |c| c.upload_file(&local_path, &remote_path)).await
+    }
+
+    async fn download(&mut self, remote_path: &str, local_path: &Path, _size: u64, _app: &AppHandle) -> Result<(), String> {
+        if let Some(parent) = local_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let (remote_path, local_path) = (remote_path.to_string(), local_path.to_string_lossy().to_string());
+        blocking(self, move |c| c.download_file(&remote_path, &local_path)).await
+    }
+}
+
+#[async_trait]
+impl RemoteBackend for WebDavClient {
+    async fn is_alive(&mut self) -> bool {
+        true
+    }
+
+    async fn list(&mut self, path: &str) -> Result<Vec<FileItem>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.list_directory(&path)).await
+    }
+
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.read_file(&path)).await
+    }
+
+    async fn write(&mut self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.write_file(&path, &data)).await
+    }
+
+    async fn mkdir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.create_directory(&path)).await
+    }
+
+    async fn remove_file(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove(&path)).await
+    }
+
+    async fn remove_dir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove(&path)).await
+    }
+
+    async fn rename(&mut self, src: &str, dst: &str) -> Result<(), String> {
+        let (src, dst) = (src.to_string(), dst.to_string());
+        blocking(self, move |c| c.rename_file(&src, &dst)).await
+    }
+
+    async fn upload(&mut self, local_path: &Path, remote_path: &str, _app: &AppHandle) -> Result<(), String> {
+        let (local_path, remote_path) = (local_path.to_string_lossy().to_string(), remote_path.to_string());
+        blocking(self, move |c| c.upload_file(&local_path, &remote_path)).await
+    }
+
+    async fn download(&mut self, remote_path: &str, local_path: &Path, _size: u64, _app: &AppHandle) -> Result<(), String> {
+        if let Some(parent) = local_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let (remote_path, local_path) = (remote_path.to_string(), local_path.to_string_lossy().to_string());
+        blocking(self, move |c| c.download_file(&remote_path, &local_path)).await
+    }
+}
+
+#[async_trait]
+impl RemoteBackend for S3Client {
+    async fn is_alive(&mut self) -> bool {
+        true
+    }
+
+    async fn list(&mut self, path: &str) -> Result<Vec<FileItem>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.list_directory(&path)).await
+    }
+
+    async fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.read_file(&path)).await
+    }
+
+    async fn write(&mut self, path: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.write_file(&path, &data)).await
+    }
+
+    async fn mkdir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.create_directory(&path)).await
+    }
+
+    async fn remove_file(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove(&path)).await
+    }
+
+    async fn remove_dir(&mut self, path: &str) -> Result<(), String> {
+        let path = path.to_string();
+        blocking(self, move |c| c.remove_directory(&path)).await
+    }
+
+    async fn rename(&mut self, src: &str, dst: &str) -> Result<(), String> {
+        let (src, dst) = (src.to_string(), dst.to_string());
+        blocking(self, move |c| c.rename_file(&src, &dst)).await
+    }
+
+    async fn upload(&mut self, local_path: &Path, remote_path: &str, _app: &AppHandle) -> Result<(), String> {
+        let (local_path, remote_path) = (local_path.to_string_lossy().to_string(), remote_path.to_string());
+        blocking(self, move |c| c.upload_file(&local_path, &remote_path)).await
+    }
+
+    async fn download(&mut self, remote_path: &str, local_path: &Path, _size: u64, _app: &AppHandle) -> Result<(), String> {
+        if let Some(parent) = local_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let (remote_path, local_path) = (remote_path.to_string(), local_path.to_string_lossy().to_string());
+        blocking(self, move |c| c.download_file(&remote_path, &local_path)).await
+    }
+}
+
+/// A session's client, checked out for the length of one operation. The FTP control connection
+/// stays locked meanwhile; the other clients are clones of the cached one.
+enum SessionBackend<'a> {
+    Ftp(MutexGuard<'a, Option<FtpConnection>>),
+    Sftp(SftpClient),
+    WebDav(WebDavClient),
+    S3(S3Client),
+}
+
+impl SessionBackend<'_> {
+    fn backend(&mut self) -> Result<&mut dyn RemoteBackend, String> {
+        match self {
+            SessionBackend::Ftp(guard) => guard.as_mut()
+                .map(|stream| stream as &mut dyn RemoteBackend)
+                .ok_or_else(|| "Not connected".to_string()),
+            SessionBackend::Sftp(client) => Ok(client),
+            SessionBackend::WebDav(client) => Ok(client),
+            SessionBackend::S3(client) => Ok(client),
+        }
+    }
+}
+
+/// Returns the session's client for its protocol, reconnecting first if it has dropped.
+async fn acquire(session: &RemoteSession) -> Result<SessionBackend<'_>, String> {
+    let protocol = session.connection_info.lock().await.as_ref().map(|c| c.protocol.clone());
+    match protocol {
+        Some(ConnectionProtocol::SFTP) => get_or_create_sftp_client(session).await
+            .map(SessionBackend::Sftp)
+            .map_err(|e| format!("Failed to connect SFTP: {}", e)),
+        // WebDAV and S3 clients hold no connection, so the one made at login stays usable.
+        Some(ConnectionProtocol::WebDAV) | Some(ConnectionProtocol::WebDAVS) => session.webdav_client.lock().await.clone()
+            .map(SessionBackend::WebDav)
+            .ok_or_else(|| "Not connected".to_string()),
+        Some(ConnectionProtocol::S3) => session.s3_client.lock().await.clone()
+            .map(SessionBackend::S3)
+            .ok_or_else(|| "Not connected".to_string()),
+        Some(ConnectionProtocol::FTP) | Some(ConnectionProtocol::FTPS) | Some(ConnectionProtocol::FTPSImplicit) | None => {
+            get_or_reconnect_stream(session).await?;
+            Ok(SessionBackend::Ftp(session.ftp_client.lock().await))
+        }
+    }
+}
+
+/// The future an operation passed to [`with_backend`] returns. It may borrow the backend but
+/// must own everything else it uses, since it can run twice.
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// Runs `op` against the session's client, reconnecting first if the connection has dropped.
+/// A failure is returned as is, even when the connection was lost midway: `op` may have changed
+/// the server before it failed, so only the caller can tell whether running it again is safe.
+/// Failures are recorded on the session's transcript, along with the FTP traffic they caused.
+pub async fn with_backend<T, F>(session: &RemoteSession, op: F) -> Result<T, String>
+where
+    F: for<'a> Fn(&'a mut dyn RemoteBackend) -> BackendFuture<'a, T>,
+{
+    logged(session, run_with_backend(session, op, false)).await
+}
+
+/// Like [`with_backend`], but if `op` fails because the connection was lost, the client is
+/// reconnected and `op` runs once more. Only for operations that leave the server unchanged.
+pub async fn with_backend_retrying<T, F>(session: &RemoteSession, op: F) -> Result<T, String>
+where
+    F: for<'a> Fn(&'a mut dyn RemoteBackend) -> BackendFuture<'a, T>,
+{
+    logged(session, run_with_backend(session, op, true)).await
+}
+
+async fn logged<T>(session: &RemoteSession, run: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    let result = session.log.scope(run).await;
+    if let Err(e) = &result {
+        session.log.error(e.clone());
+    }
+    result
+}
+
+async fn run_with_backend<T, F>(session: &RemoteSession, op: F, retry: bool) -> Result<T, String>
+where
+    F: for<'a> Fn(&'a mut dyn RemoteBackend) -> BackendFuture<'a, T>,
+{
+    let mut handle = acquire(session).await?;
+    let error = match op(handle.backend()?).await {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    if handle.backend()?.is_alive().await {
+        return Err(error);
+    }
+    drop(handle);
+    if !retry {
+        return Err(format!("{} (the connection was lost and will be reopened by the next operation)", error));
+    }
+
+    session.log.error(format!("Connection lost during remote operation ({}), reconnecting", error));
+    let mut handle = acquire(session).await?;
+    op(handle.backend()?).await
+        .map_err(|e| format!("{} (after reconnect)", e))
+}
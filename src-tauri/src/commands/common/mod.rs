@@ -1,6 +1,7 @@
 pub mod sftp_helper;
+pub mod backend;
 
-use crate::models::FtpState;
+use crate::models::{ConnectionState, RemoteSession};
 use crate::reconnect::{reconnect_with_retry, is_connection_alive};
 
 pub fn normalize_remote_path(path: &str) -> String {
@@ -37,8 +38,8 @@ pub fn normalize_remote_path(path: &str) -> String {
     path.to_string()
 }
 
-pub async fn get_or_reconnect_stream(state: &FtpState) -> Result<(), String> {
-    let conn_info_guard = state.connection_info.lock().await;
+pub async fn get_or_reconnect_stream(session: &RemoteSession) -> Result<(), String> {
+    let conn_info_guard = session.connection_info.lock().await;
     let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
     let conn_info_clone = conn_info_guard.as_ref().cloned();
     drop(conn_info_guard);
@@ -48,21 +49,29 @@ pub async fn get_or_reconnect_stream(state: &FtpState) -> Result<(), String> {
             Ok(())
         },
         _ => {
-            let mut client_guard = state.ftp_client.lock().await;
+            let mut client_guard = session.ftp_client.lock().await;
             if let Some(stream) = client_guard.as_mut() {
-                if is_connection_alive(stream).await {
+                let timeout = conn_info_clone.as_ref()
+                    .map(|c| c.options.read_timeout())
+                    .unwrap_or(std::time::Duration::from_secs(30));
+                if is_connection_alive(stream, timeout).await {
                     return Ok(());
                 }
                 let _ = client_guard.take();
             }
             
             if let Some(conn_info) = conn_info_clone {
+                session.emit_state(ConnectionState::Reconnecting, None);
                 match reconnect_with_retry(&conn_info, 3).await {
                     Ok(new_stream) => {
                         *client_guard = Some(new_stream);
+                        session.emit_state(ConnectionState::Connected, None);
                         Ok(())
                     }
-                    Err(e) => Err(format!("Auto-reconnect failed: {}", e))
+                    Err(e) => {
+                        session.emit_state(ConnectionState::Lost, Some(e.clone()));
+                        Err(format!("Auto-reconnect failed: {}", e))
+                    }
                 }
             } else {
                 Err("Not connected and no connection info available".to_string())
@@ -72,3 +81,4 @@ pub async fn get_or_reconnect_stream(state: &FtpState) -> Result<(), String> {
 }
 
 pub use sftp_helper::*;
+pub use backend::{RemoteBackend, with_backend, with_backend_retrying};
@@ -1,19 +1,40 @@
-use crate::models::FtpState;
+use crate::models::{ConnectionState, RemoteSession};
 use crate::sftp_ops::SftpClient;
-use tauri::State;
-pub async fn get_or_create_sftp_client(state: &State<'_, FtpState>) -> Result<SftpClient, String> {
-    {
-        let sftp_session_guard = state.sftp_session.lock().await;
-        let sftp_tcp_guard = state.sftp_tcp.lock().await;
-        
-        if sftp_session_guard.is_some() && sftp_tcp_guard.is_some() {
+
+pub async fn get_or_create_sftp_client(session: &RemoteSession) -> Result<SftpClient, String> {
+    let mut client_guard = session.sftp_client.lock().await;
+    if let Some(client) = client_guard.as_ref() {
+        let alive_client = client.clone();
+        let alive = tokio::task::spawn_blocking(move || alive_client.is_alive())
+            .await
+            .unwrap_or(false);
+        if alive {
+            return Ok(client.clone());
         }
+        let _ = client_guard.take();
     }
 
-    let conn_info_guard = state.connection_info.lock().await;
+    let conn_info_guard = session.connection_info.lock().await;
     let conn_info = conn_info_guard.as_ref()
         .ok_or("No connection info available")?
         .clone();
     drop(conn_info_guard);
-    SftpClient::connect(&conn_info.host, conn_info.port, &conn_info.username, &conn_info.password)
+
+    session.emit_state(ConnectionState::Reconnecting, None);
+    let (prompts, log) = (session.prompts.clone(), session.log.clone());
+    let result = tokio::task::spawn_blocking(move || SftpClient::connect(&conn_info, &prompts, log))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(client) => {
+            *client_guard = Some(client.clone());
+            session.emit_state(ConnectionState::Connected, None);
+            Ok(client)
+        }
+        Err(e) => {
+            session.emit_state(ConnectionState::Lost, Some(e.clone()));
+            Err(format!("Auto-reconnect failed: {}", e))
+        }
+    }
 }
@@ -1,176 +1,33 @@
 use tauri::State;
 use std::path::Path;
 use chrono::{DateTime, Utc};
-use crate::models::{FtpState, FileItem, RecentFolder, CommandResult, ConnectionProtocol};
-use crate::utils::{format_bytes, parse_ftp_list_line};
-use crate::commands::common::{normalize_remote_path, get_or_reconnect_stream};
-use crate::commands::common::sftp_helper::get_or_create_sftp_client;
+use crate::models::{FtpState, FileItem, RecentFolder, CommandResult};
+use crate::utils::format_bytes;
+use crate::commands::common::{normalize_remote_path, with_backend_retrying};
 
 
 #[tauri::command]
-pub async fn list_remote_files(state: State<'_, FtpState>, path: String) -> Result<CommandResult<Vec<FileItem>>, String> {
+pub async fn list_remote_files(state: State<'_, FtpState>, session_id: String, path: String) -> Result<CommandResult<Vec<FileItem>>, String> {
+    let session = match state.session(&session_id).await {
+        Ok(session) => session,
+        Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+    };
     let normalized_path = normalize_remote_path(&path);
-    let conn_info_guard = state.connection_info.lock().await;
-    let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-    drop(conn_info_guard);
-    
-    match protocol {
-        Some(ConnectionProtocol::SFTP) => {
-            match get_or_create_sftp_client(&state).await {
-                Ok(sftp_client) => {
-                    match sftp_client.list_directory(&normalized_path) {
-                        Ok(items) => {
-                            let mut path_guard = state.current_path.lock().await;
-                            *path_guard = normalized_path;
 
-                            Ok(CommandResult {
-                                success: true,
-                                data: Some(items),
-                                error: None,
-                            })
-                        },
-                        Err(e) => {
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to list SFTP directory: {}", e)),
-                            })
-                        }
-                    }
-                },
-                Err(e) => {
-                    Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(format!("Failed to connect SFTP for listing: {}", e)),
-                    })
-                }
-            }
-        }
-        Some(ConnectionProtocol::FTP) | None => {
-            if let Err(e) = get_or_reconnect_stream(&state).await {
-                return Ok(CommandResult {
-                    success: false,
-                    data: None,
-                    error: Some(e),
-                });
-            }
-            
-            let mut client_guard = state.ftp_client.lock().await;
-            
-            if let Some(stream) = client_guard.as_mut() {
-                match stream.mlsd(Some(&normalized_path)).await {
-                    Ok(files) => {
-                        let mut items = Vec::new();
-                        for file_str in &files {
-                            if let Some((name, size, is_directory, date_str, permissions)) = parse_ftp_list_line(file_str) {
-                                 let full_path = if normalized_path == "/" { format!("/{}", name) } else { format!("{}/{}", normalized_path.trim_end_matches('/'), name) };
-                                 let size = if is_directory {
-                                     0
-                                 } else {
-                                     size
-                                 };
-
-                                 let readable_size = if is_directory {
-                                     "".to_string()
-                                 } else {
-                                     format_bytes(size)
-                                 };
-
-                                 items.push(FileItem {
-                                    name: name.clone(),
-                                    full_path, 
-                                    size,
-                                    modified: None,
-                                    is_directory,
-                                    readable_size,
-                                    readable_modified: date_str,
-                                    permissions,
-                                });
-                            }
-                        }
-                        
-                        let mut path_guard = state.current_path.lock().await;
-                        *path_guard = normalized_path;
-
-                        Ok(CommandResult {
-                            success: true,
-                            data: Some(items),
-                            error: None,
-                        })
-                    },
-                    Err(e) => {
-                        drop(client_guard);
-                        
-                        if get_or_reconnect_stream(&state).await.is_ok() {
-                            let mut retry_guard = state.ftp_client.lock().await;
-                            if let Some(retry_stream) = retry_guard.as_mut() {
-                                match retry_stream.mlsd(Some(&normalized_path)).await {
-                                    Ok(files) => {
-                                        let mut items = Vec::new();
-                                        for file_str in &files {
-                                            if let Some((name, size, is_directory, date_str, permissions)) = parse_ftp_list_line(file_str) {
-                                                 let full_path = if normalized_path == "/" { format!("/{}", name) } else { format!("{}/{}", normalized_path.trim_end_matches('/'), name) };
-                                                 let size = if is_directory {
-                                                     0
-                                                 } else {
-                                                     size
-                                                 };
-
-                                                 let readable_size = if is_directory {
-                                                     "".to_string()
-                                                 } else {
-                                                     format_bytes(size)
-                                                 };
-
-                                                 items.push(FileItem {
-                                                    name: name.clone(),
-                                                    full_path, 
-                                                    size,
-                                                    modified: None,
-                                                    is_directory,
-                                                    readable_size,
-                                                    readable_modified: date_str,
-                                                    permissions,
-                                                });
-                                            }
-                                        }
-                                        
-                                        let mut path_guard = state.current_path.lock().await;
-                                        *path_guard = normalized_path;
-
-                                        return Ok(CommandResult {
-                                            success: true,
-                                            data: Some(items),
-                                            error: None,
-                                        });
-                                    },
-                                    Err(retry_err) => {
-                                        return Ok(CommandResult {
-                                            success: false,
-                                            data: None,
-                                            error: Some(format!("Failed after reconnect: {}", retry_err)),
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                        
-                        Ok(CommandResult {
-                            success: false,
-                            data: None,
-                            error: Some(format!("Failed to list directory via MLSD: {}", e)),
-                        })
-                    }
-                }
-            } else {
-                Ok(CommandResult {
-                    success: false,
-                    data: None,
-                    error: Some("Not connected".to_string()),
-                })
-            }
+    let listed_path = normalized_path.clone();
+    match with_backend_retrying(&session, |backend| {
+        let path = listed_path.clone();
+        Box::pin(async move { backend.list(&path).await })
+    }).await {
+        Ok(items) => {
+            *session.current_path.lock().await = normalized_path;
+            Ok(CommandResult { success: true, data: Some(items), error: None })
         }
+        Err(e) => Ok(CommandResult {
+            success: false,
+            data: None,
+            error: Some(format!("Failed to list directory: {}", e)),
+        }),
     }
 }
 
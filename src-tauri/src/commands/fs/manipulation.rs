@@ -1,11 +1,7 @@
 use tauri::{AppHandle, State, Emitter};
 use std::path::Path;
-use suppaftp::AsyncFtpStream;
-use crate::models::{FtpState, CommandResult, ConnectionProtocol};
-use crate::utils::parse_ftp_list_line;
-use crate::commands::common::{normalize_remote_path, get_or_reconnect_stream};
-use crate::sftp_ops::SftpClient;
-use futures_lite::io::Cursor as AsyncCursor;
+use crate::models::{FtpState, CommandResult};
+use crate::commands::common::{normalize_remote_path, with_backend, RemoteBackend};
 
 #[derive(Clone, serde::Serialize)]
 struct DeleteProgress {
@@ -16,235 +12,97 @@ struct DeleteProgress {
     deleted_items: u32,
 }
 
-pub async fn count_remote_items(stream: &mut AsyncFtpStream, path: &str) -> Result<u32, String> {
-    match stream.mlsd(Some(path)).await {
-        Ok(files) => {
-            let mut count = 1u32;
-            
-            for file_str in files {
-                if let Some((name, _, is_directory, _, _)) = parse_ftp_list_line(&file_str) {
-                    if name == "." || name == ".." { continue; }
-                    
-                    let full_path = format!("{}/{}", path.trim_end_matches('/'), name);
-                    if is_directory {
-                        count += Box::pin(count_remote_items(stream, &full_path)).await?;
-                    } else {
-                        count += 1;
-                    }
-                }
-            }
-            
-            Ok(count)
-        },
-        Err(_) => {
-            Ok(1)
+pub async fn count_remote_items(backend: &mut dyn RemoteBackend, path: &str) -> Result<u32, String> {
+    let mut count = 1u32;
+    for item in backend.list(path).await? {
+        if item.is_directory {
+            count += Box::pin(count_remote_items(backend, &item.full_path)).await?;
+        } else {
+            count += 1;
         }
     }
+    Ok(count)
+}
+
+fn emit_delete_progress(app: &AppHandle, file_name: &str, current_item: &str, total_items: u32, deleted_items: u32) {
+    let payload = DeleteProgress {
+        file_name: file_name.to_string(),
+        progress: (deleted_items as f64 / total_items as f64) * 100.0,
+        current_item: current_item.to_string(),
+        total_items,
+        deleted_items,
+    };
+    let _ = app.emit("delete-progress", payload);
 }
 
 async fn delete_remote_recursive(
-    stream: &mut AsyncFtpStream, 
+    backend: &mut dyn RemoteBackend,
     path: &str,
     app: &AppHandle,
-    file_name: String,
+    file_name: &str,
     total_items: u32,
     deleted_items: &mut u32
 ) -> Result<(), String> {
-    match stream.mlsd(Some(path)).await {
-        Ok(files) => {
-            for file_str in files {
-                if let Some((name, _, is_directory, _, _)) = parse_ftp_list_line(&file_str) {
-                    if name == "." || name == ".." { continue; }
-                    
-                    let full_path = format!("{}/{}", path.trim_end_matches('/'), name);
-                    if is_directory {
-                        Box::pin(delete_remote_recursive(stream, &full_path, app, file_name.clone(), total_items, deleted_items)).await?;
-                    } else {
-                        if stream.rm(&full_path).await.is_ok() {
-                            *deleted_items += 1;
-                            let progress = (*deleted_items as f64 / total_items as f64) * 100.0;
-                            let payload = DeleteProgress {
-                                file_name: file_name.clone(),
-                                progress,
-                                current_item: name.clone(),
-                                total_items,
-                                deleted_items: *deleted_items,
-                            };
-                            let _ = app.emit("delete-progress", payload);
-                        } else {
-                            let error_msg = format!("Failed to delete file: {}", full_path);
-                            eprintln!("Delete error: {}", error_msg);
-                            return Err(error_msg);
-                        }
-                    }
-                }
-            }
-            stream.rmdir(path).await.map_err(|e| format!("Failed to remove directory {}: {}", path, e))?;
+    for item in backend.list(path).await? {
+        if item.is_directory {
+            Box::pin(delete_remote_recursive(backend, &item.full_path, app, file_name, total_items, deleted_items)).await?;
+        } else {
+            backend.remove_file(&item.full_path).await?;
             *deleted_items += 1;
-            let progress = (*deleted_items as f64 / total_items as f64) * 100.0;
-            let payload = DeleteProgress {
-                file_name: file_name.clone(),
-                progress,
-                current_item: path.split('/').last().unwrap_or(path).to_string(),
-                total_items,
-                deleted_items: *deleted_items,
-            };
-            let _ = app.emit("delete-progress", payload);
-        },
-        Err(_) => {
-            if stream.rm(path).await.is_ok() {
-                *deleted_items += 1;
-                let progress = (*deleted_items as f64 / total_items as f64) * 100.0;
-                let payload = DeleteProgress {
-                    file_name: file_name.clone(),
-                    progress,
-                    current_item: path.split('/').last().unwrap_or(path).to_string(),
-                    total_items,
-                    deleted_items: *deleted_items,
-                };
-                let _ = app.emit("delete-progress", payload);
-            } else {
-                let error_msg = format!("Failed to delete file: {}", path);
-                eprintln!("Delete error: {}", error_msg);
-                return Err(error_msg);
-            }
+            emit_delete_progress(app, file_name, &item.name, total_items, *deleted_items);
         }
     }
-    
+    backend.remove_dir(path).await?;
+    *deleted_items += 1;
+    emit_delete_progress(app, file_name, path.rsplit('/').next().unwrap_or(path), total_items, *deleted_items);
     Ok(())
 }
 
+/// Deletes a remote file, or a directory with everything in it.
+async fn delete_remote_item(backend: &mut dyn RemoteBackend, path: &str, app: &AppHandle) -> Result<(), String> {
+    let file_name = path.rsplit('/').next().unwrap_or("unknown").to_string();
+    if !backend.stat(path).await?.is_directory {
+        backend.remove_file(path).await?;
+        emit_delete_progress(app, &file_name, &file_name, 1, 1);
+        return Ok(());
+    }
+
+    let total_items = count_remote_items(backend, path).await
+        .map_err(|e| format!("Failed to count items: {}", e))?;
+    let mut deleted_items = 0u32;
+    delete_remote_recursive(backend, path, app, &file_name, total_items, &mut deleted_items).await
+}
+
 #[tauri::command]
 pub async fn delete_file(
     app: AppHandle,
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     is_remote: bool,
 ) -> Result<CommandResult<()>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_path = normalize_remote_path(&path);
-        eprintln!("Attempting to delete remote file: {} -> {}", path, normalized_path);
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-        
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                let conn_info = state.connection_info.lock().await;
-                if let Some(ref conn) = *conn_info {
-                    let host = conn.host.clone();
-                    let port = conn.port;
-                    let username = conn.username.clone();
-                    let password = conn.password.clone();
-                    drop(conn_info);
-                    
-                    match SftpClient::connect(&host, port, &username, &password) {
-                        Ok(sftp_client) => {
-                            let path_obj = Path::new(&normalized_path);
-                            let result = if path_obj.is_dir() {
-                                match sftp_client.remove_directory(&normalized_path) {
-                                    Ok(_) => Ok(()),
-                                    Err(e) => Err(format!("Failed to remove SFTP directory: {}", e))
-                                }
-                            } else {
-                                match sftp_client.remove_file(&normalized_path) {
-                                    Ok(_) => Ok(()),
-                                    Err(e) => Err(format!("Failed to remove SFTP file: {}", e))
-                                }
-                            };
-                            
-                            match result {
-                                Ok(_) => {
-                                    eprintln!("SFTP Delete successful for: {}", normalized_path);
-                                    Ok(CommandResult { success: true, data: None, error: None })
-                                },
-                                Err(e) => {
-                                    eprintln!("SFTP Delete failed for {}: {}", normalized_path, e);
-                                    Ok(CommandResult {
-                                        success: false,
-                                        data: None,
-                                        error: Some(e),
-                                    })
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to connect SFTP for deletion: {}", e)),
-                            })
-                        }
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("No connection info available".to_string()) })
-                }
-            }
-            Some(ConnectionProtocol::FTP) | None => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    eprintln!("Reconnect failed: {}", e);
-                    return Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(e),
-                    });
-                }
-                
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    let total_items = match count_remote_items(stream, &normalized_path).await {
-                        Ok(count) => count,
-                        Err(e) => {
-                            return Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to count items: {}", e)),
-                            });
-                        }
-                    };
+        session.log.info(format!("Deleting {}", normalized_path));
+        let result = with_backend(&session, |backend| {
+            let (path, app) = (normalized_path.clone(), app.clone());
+            Box::pin(async move { delete_remote_item(backend, &path, &app).await })
+        }).await;
 
-                    let file_name = normalized_path.split('/').last().unwrap_or("unknown").to_string();
-                    let mut deleted_items = 0u32;
-
-                    match delete_remote_recursive(stream, &normalized_path, &app, file_name.clone(), total_items, &mut deleted_items).await {
-                        Ok(_) => {
-                            eprintln!("Delete successful for: {}", normalized_path);
-                            Ok(CommandResult { success: true, data: None, error: None })
-                        },
-                        Err(e) => {
-                            eprintln!("Delete failed for {}: {}", normalized_path, e);
-                            drop(client_guard);
-                            
-                            if get_or_reconnect_stream(&state).await.is_ok() {
-                                let mut retry_guard = state.ftp_client.lock().await;
-                                if let Some(retry_stream) = retry_guard.as_mut() {
-                                    let mut retry_deleted_items = 0u32;
-                                    match delete_remote_recursive(retry_stream, &normalized_path, &app, file_name.clone(), total_items, &mut retry_deleted_items).await {
-                                        Ok(_) => {
-                                            return Ok(CommandResult { success: true, data: None, error: None });
-                                        },
-                                        Err(retry_err) => {
-                                            return Ok(CommandResult {
-                                                success: false,
-                                                data: None,
-                                                error: Some(format!("Failed to delete after reconnect: {}", retry_err)),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to delete remote item: {}", e)),
-                            })
-                        },
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        match result {
+            Ok(_) => {
+                session.log.info(format!("Deleted {}", normalized_path));
+                Ok(CommandResult { success: true, data: None, error: None })
+            },
+            Err(e) => Ok(CommandResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to delete remote item: {}", e)),
+            }),
         }
     } else {
         let result = tokio::task::spawn_blocking(move || {
@@ -266,95 +124,30 @@ pub async fn delete_file(
 #[tauri::command]
 pub async fn rename_file(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     old_path: String,
     new_path: String,
     is_remote: bool,
 ) -> Result<CommandResult<()>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_old_path = normalize_remote_path(&old_path);
         let normalized_new_path = normalize_remote_path(&new_path);
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-        
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                let conn_info = state.connection_info.lock().await;
-                if let Some(ref conn) = *conn_info {
-                    let host = conn.host.clone();
-                    let port = conn.port;
-                    let username = conn.username.clone();
-                    let password = conn.password.clone();
-                    drop(conn_info);
-                    
-                    match SftpClient::connect(&host, port, &username, &password) {
-                        Ok(sftp_client) => {
-                            match sftp_client.rename_file(&normalized_old_path, &normalized_new_path) {
-                                Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                                Err(e) => Ok(CommandResult {
-                                    success: false,
-                                    data: None,
-                                    error: Some(format!("Failed to rename SFTP item: {}", e)),
-                                })
-                            }
-                        },
-                        Err(e) => {
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to connect SFTP for rename: {}", e)),
-                            })
-                        }
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("No connection info available".to_string()) })
-                }
-            }
-            Some(ConnectionProtocol::FTP) | None => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(e),
-                    });
-                }
-                
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    match stream.rename(&normalized_old_path, &normalized_new_path).await {
-                        Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                        Err(e) => {
-                            drop(client_guard);
-                            
-                            if get_or_reconnect_stream(&state).await.is_ok() {
-                                let mut retry_guard = state.ftp_client.lock().await;
-                                if let Some(retry_stream) = retry_guard.as_mut() {
-                                    match retry_stream.rename(&normalized_old_path, &normalized_new_path).await {
-                                        Ok(_) => {
-                                            return Ok(CommandResult { success: true, data: None, error: None });
-                                        },
-                                        Err(retry_err) => {
-                                            return Ok(CommandResult {
-                                                success: false,
-                                                data: None,
-                                                error: Some(format!("Failed to rename after reconnect: {}", retry_err)),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to rename remote item: {}", e)),
-                            })
-                        }
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        let result = with_backend(&session, |backend| {
+            let (old_path, new_path) = (normalized_old_path.clone(), normalized_new_path.clone());
+            Box::pin(async move { backend.rename(&old_path, &new_path).await })
+        }).await;
+
+        match result {
+            Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
+            Err(e) => Ok(CommandResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to rename remote item: {}", e)),
+            }),
         }
     } else {
         let result = tokio::task::spawn_blocking(move || {
@@ -371,93 +164,28 @@ pub async fn rename_file(
 #[tauri::command]
 pub async fn create_directory(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     is_remote: bool,
 ) -> Result<CommandResult<()>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_path = normalize_remote_path(&path);
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-        
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                let conn_info = state.connection_info.lock().await;
-                if let Some(ref conn) = *conn_info {
-                    let host = conn.host.clone();
-                    let port = conn.port;
-                    let username = conn.username.clone();
-                    let password = conn.password.clone();
-                    drop(conn_info);
-                    
-                    match SftpClient::connect(&host, port, &username, &password) {
-                        Ok(sftp_client) => {
-                            match sftp_client.create_directory(&normalized_path) {
-                                Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                                Err(e) => Ok(CommandResult {
-                                    success: false,
-                                    data: None,
-                                    error: Some(format!("Failed to create SFTP directory: {}", e)),
-                                })
-                            }
-                        },
-                        Err(e) => {
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to connect SFTP for directory creation: {}", e)),
-                            })
-                        }
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("No connection info available".to_string()) })
-                }
-            }
-            Some(ConnectionProtocol::FTP) | None => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(e),
-                    });
-                }
-                
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    match stream.mkdir(&normalized_path).await {
-                        Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                        Err(e) => {
-                            drop(client_guard);
-                            
-                            if get_or_reconnect_stream(&state).await.is_ok() {
-                                let mut retry_guard = state.ftp_client.lock().await;
-                                if let Some(retry_stream) = retry_guard.as_mut() {
-                                    match retry_stream.mkdir(&normalized_path).await {
-                                        Ok(_) => {
-                                            return Ok(CommandResult { success: true, data: None, error: None });
-                                        },
-                                        Err(retry_err) => {
-                                            return Ok(CommandResult {
-                                                success: false,
-                                                data: None,
-                                                error: Some(format!("Failed to create directory after reconnect: {}", retry_err)),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to create remote directory: {}", e)),
-                            })
-                        },
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        let result = with_backend(&session, |backend| {
+            let path = normalized_path.clone();
+            Box::pin(async move { backend.mkdir(&path).await })
+        }).await;
+
+        match result {
+            Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
+            Err(e) => Ok(CommandResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to create remote directory: {}", e)),
+            }),
         }
     } else {
         let result = tokio::task::spawn_blocking(move || {
@@ -474,95 +202,28 @@ pub async fn create_directory(
 #[tauri::command]
 pub async fn create_file(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     is_remote: bool,
 ) -> Result<CommandResult<()>, String> {
     if is_remote {
-        let normalized_path = normalize_remote_path(&path);    
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-        
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                let conn_info = state.connection_info.lock().await;
-                if let Some(ref conn) = *conn_info {
-                    let host = conn.host.clone();
-                    let port = conn.port;
-                    let username = conn.username.clone();
-                    let password = conn.password.clone();
-                    drop(conn_info);
-                    
-                    match SftpClient::connect(&host, port, &username, &password) {
-                        Ok(sftp_client) => {
-                            match sftp_client.create_empty_file(&normalized_path) {
-                                Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                                Err(e) => Ok(CommandResult {
-                                    success: false,
-                                    data: None,
-                                    error: Some(format!("Failed to create SFTP file: {}", e)),
-                                })
-                            }
-                        },
-                        Err(e) => {
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to connect SFTP for file creation: {}", e)),
-                            })
-                        }
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("No connection info available".to_string()) })
-                }
-            }
-            Some(ConnectionProtocol::FTP) | None => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(e),
-                    });
-                }
-                
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    let mut cursor = AsyncCursor::new(Vec::new());
-                    match stream.put_file(&normalized_path, &mut cursor).await {
-                        Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                        Err(e) => {
-                            drop(client_guard);
-                            
-                            if get_or_reconnect_stream(&state).await.is_ok() {
-                                let mut retry_guard = state.ftp_client.lock().await;
-                                if let Some(retry_stream) = retry_guard.as_mut() {
-                                    let mut retry_cursor = AsyncCursor::new(Vec::new());
-                                    match retry_stream.put_file(&normalized_path, &mut retry_cursor).await {
-                                        Ok(_) => {
-                                            return Ok(CommandResult { success: true, data: None, error: None });
-                                        },
-                                        Err(retry_err) => {
-                                            return Ok(CommandResult {
-                                                success: false,
-                                                data: None,
-                                                error: Some(format!("Failed to create file after reconnect: {}", retry_err)),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            Ok(CommandResult {
-                                success: false,
-                                data: None,
-                                error: Some(format!("Failed to create remote file: {}", e)),
-                            })
-                        },
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
+        let normalized_path = normalize_remote_path(&path);
+        let result = with_backend(&session, |backend| {
+            let path = normalized_path.clone();
+            Box::pin(async move { backend.write(&path, Vec::new()).await })
+        }).await;
+
+        match result {
+            Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
+            Err(e) => Ok(CommandResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to create remote file: {}", e)),
+            }),
         }
     } else {
         let result = tokio::task::spawn_blocking(move || {
@@ -2,21 +2,25 @@ use tauri::State;
 use std::path::Path;
 use async_recursion::async_recursion;
 use ignore::WalkBuilder;
-use crate::models::{FtpState, FileItem, CommandResult, ConnectionProtocol};
-use crate::utils::{format_bytes, parse_ftp_list_line};
-use crate::commands::common::{normalize_remote_path, get_or_reconnect_stream};
-use crate::commands::common::sftp_helper::get_or_create_sftp_client;
+use crate::models::{FtpState, RemoteSession, FileItem, CommandResult};
+use crate::utils::format_bytes;
+use crate::commands::common::{normalize_remote_path, with_backend_retrying, RemoteBackend};
 
 #[tauri::command]
 pub async fn search_files(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     query: String,
     is_remote: bool,
     _recursive: bool,
 ) -> Result<CommandResult<Vec<FileItem>>, String> {
     if is_remote {
-        search_remote(state, path, query, true).await
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
+        search_remote(&session, path, query, true).await
     } else {
         search_local(path, query, true).await
     }
@@ -98,125 +102,36 @@ async fn search_local(path: String, query: String, recursive: bool) -> Result<Co
     }
 }
 
-async fn search_remote(state: State<'_, FtpState>, path: String, query: String, recursive: bool) -> Result<CommandResult<Vec<FileItem>>, String> {
+async fn search_remote(session: &RemoteSession, path: String, query: String, recursive: bool) -> Result<CommandResult<Vec<FileItem>>, String> {
     let normalized_path = normalize_remote_path(&path);
-    let conn_info_guard = state.connection_info.lock().await;
-    let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-    drop(conn_info_guard);
-
-    match protocol {
-        Some(ConnectionProtocol::SFTP) => {
-            match get_or_create_sftp_client(&state).await {
-                Ok(sftp_client) => {
-                    let mut items = Vec::new();
-                    if recursive {
-                        search_sftp_recursive(&sftp_client, &normalized_path, &query, &mut items)?;
-                    } else {
-                        match sftp_client.list_directory(&normalized_path) {
-                            Ok(remote_items) => {
-                                for item in remote_items {
-                                    if matches_query(&item.name, &query) {
-                                        items.push(item);
-                                    }
-                                }
-                            },
-                            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e.to_string()) }),
-                        }
-                    }
-                    Ok(CommandResult { success: true, data: Some(items), error: None })
-                },
-                Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
-            }
-        }
-        Some(ConnectionProtocol::FTP) | None => {
-            if let Err(e) = get_or_reconnect_stream(&state).await {
-                return Ok(CommandResult { success: false, data: None, error: Some(e) });
-            }
-            
+    let result = with_backend_retrying(session, |backend| {
+        let (path, query) = (normalized_path.clone(), query.clone());
+        Box::pin(async move {
             let mut items = Vec::new();
             if recursive {
-                search_ftp_recursive(&state, normalized_path, &query, &mut items).await?;
+                search_remote_recursive(backend, &path, &query, &mut items).await?;
             } else {
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    match stream.mlsd(Some(&normalized_path)).await {
-                        Ok(files) => {
-                            for file_str in &files {
-                                if let Some((name, size, is_directory, date_str, permissions)) = parse_ftp_list_line(file_str) {
-                                    if matches_query(&name, &query) {
-                                        let full_path = if normalized_path == "/" { format!("/{}", name) } else { format!("{}/{}", normalized_path.trim_end_matches('/'), name) };
-                                        items.push(FileItem {
-                                            name,
-                                            full_path,
-                                            size: if is_directory { 0 } else { size },
-                                            modified: None,
-                                            is_directory,
-                                            readable_size: if is_directory { "".to_string() } else { format_bytes(size) },
-                                            readable_modified: date_str,
-                                            permissions,
-                                        });
-                                    }
-                                }
-                            }
-                        },
-                        Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e.to_string()) }),
-                    }
-                }
+                items.extend(backend.list(&path).await?.into_iter().filter(|item| matches_query(&item.name, &query)));
             }
-            Ok(CommandResult { success: true, data: Some(items), error: None })
-        }
+            Ok(items)
+        })
+    }).await;
+
+    match result {
+        Ok(items) => Ok(CommandResult { success: true, data: Some(items), error: None }),
+        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
     }
 }
 
-fn search_sftp_recursive(client: &crate::sftp_ops::SftpClient, path: &str, query: &str, items: &mut Vec<FileItem>) -> Result<(), String> {
-    let remote_items = client.list_directory(path).map_err(|e| e.to_string())?;
-    for item in remote_items {
+/// Unreadable subdirectories are skipped rather than failing the whole search.
+#[async_recursion]
+async fn search_remote_recursive(backend: &mut dyn RemoteBackend, path: &str, query: &str, items: &mut Vec<FileItem>) -> Result<(), String> {
+    for item in backend.list(path).await? {
         if matches_query(&item.name, query) {
             items.push(item.clone());
         }
-        if item.is_directory && item.name != "." && item.name != ".." {
-            if !item.name.starts_with('.') || query.starts_with('.') {
-                let _ = search_sftp_recursive(client, &item.full_path, query, items);
-            }
-        }
-    }
-    Ok(())
-}
-
-#[async_recursion]
-async fn search_ftp_recursive(state: &State<'_, FtpState>, path: String, query: &str, items: &mut Vec<FileItem>) -> Result<(), String> {
-    let mut client_guard = state.ftp_client.lock().await;
-    let files = if let Some(stream) = client_guard.as_mut() {
-        stream.mlsd(Some(&path)).await.map_err(|e| e.to_string())?
-    } else {
-        return Err("Not connected".to_string());
-    };
-    drop(client_guard);
-
-    for file_str in &files {
-        if let Some((name, size, is_directory, date_str, permissions)) = parse_ftp_list_line(file_str) {
-            if name == "." || name == ".." { continue; }
-            
-            let full_path = if path == "/" { format!("/{}", name) } else { format!("{}/{}", path.trim_end_matches('/'), name) };
-            
-            if matches_query(&name, query) {
-                items.push(FileItem {
-                    name: name.clone(),
-                    full_path: full_path.clone(),
-                    size: if is_directory { 0 } else { size },
-                    modified: None,
-                    is_directory,
-                    readable_size: if is_directory { "".to_string() } else { format_bytes(size) },
-                    readable_modified: date_str,
-                    permissions,
-                });
-            }
-            
-            if is_directory {
-                if !name.starts_with('.') || query.starts_with('.') {
-                    let _ = search_ftp_recursive(state, full_path, query, items).await;
-                }
-            }
+        if item.is_directory && (!item.name.starts_with('.') || query.starts_with('.')) {
+            let _ = search_remote_recursive(backend, &item.full_path, query, items).await;
         }
     }
     Ok(())
@@ -0,0 +1,45 @@
+use tauri::{AppHandle, State};
+use crate::models::{FtpState, CommandResult};
+use crate::host_keys::{self, KnownHostEntry};
+
+#[tauri::command]
+pub async fn respond_host_key_prompt(
+    state: State<'_, FtpState>,
+    request_id: String,
+    accept: bool,
+) -> Result<CommandResult<()>, String> {
+    let responses = if accept { Some(Vec::new()) } else { None };
+    if state.prompts.answer(&request_id, responses) {
+        Ok(CommandResult { success: true, data: None, error: None })
+    } else {
+        Ok(CommandResult {
+            success: false,
+            data: None,
+            error: Some("No pending host key prompt with this id".to_string()),
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn list_known_hosts(app_handle: AppHandle) -> Result<CommandResult<Vec<KnownHostEntry>>, String> {
+    let result = tokio::task::spawn_blocking(move || host_keys::list_known_hosts(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(entries) => Ok(CommandResult { success: true, data: Some(entries), error: None }),
+        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
+    }
+}
+
+#[tauri::command]
+pub async fn remove_known_host(app_handle: AppHandle, host: String) -> Result<CommandResult<u32>, String> {
+    let result = tokio::task::spawn_blocking(move || host_keys::remove_known_host(&app_handle, &host))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(removed) => Ok(CommandResult { success: true, data: Some(removed), error: None }),
+        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
+    }
+}
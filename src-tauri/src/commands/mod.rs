@@ -1,10 +1,16 @@
 pub mod common;
 pub mod connection;
+pub mod host_keys;
+pub mod sites;
+pub mod vault;
 pub mod fs;
 pub mod transfer;
 pub mod system;
 
 pub use connection::*;
+pub use host_keys::*;
+pub use sites::*;
+pub use vault::*;
 pub use fs::*;
 pub use transfer::*;
 pub use system::*;
@@ -0,0 +1,259 @@
+use tauri::{AppHandle, State};
+use crate::models::{FtpState, CommandResult, ConnectedSession, SiteConnection};
+use crate::sites::{self, SiteProfile};
+use crate::site_import::{self, ImportReport};
+use crate::vault::{JumpHostSecrets, Vault, VaultEntry};
+use crate::commands::connection::{enter_remote_dir, open_session, open_auto_session, register_session};
+use crate::commands::common::normalize_remote_path;
+
+fn to_result<T>(result: Result<T, String>) -> Result<CommandResult<T>, String> {
+    match result {
+        Ok(data) => Ok(CommandResult { success: true, data: Some(data), error: None }),
+        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
+    }
+}
+
+/// Moves the password, key passphrase and proxy password out of the profile so they are only ever
+/// written to the vault.
+fn take_secrets(site: &mut SiteProfile) -> Option<VaultEntry> {
+    let secrets = VaultEntry {
+        password: site.password.take(),
+        key_passphrase: site.options.key_passphrase.take(),
+        proxy_password: site.options.proxy.as_mut().and_then(|proxy| proxy.password.take()),
+        jump_hosts: site.options.jump_hosts.iter_mut()
+            .map(JumpHostSecrets::take)
+            .filter(|secrets| !secrets.is_empty())
+            .collect(),
+    };
+    if secrets.is_empty() { None } else { Some(secrets) }
+}
+
+/// Saves the profile, then merges any provided secrets into its vault entry. An empty string clears a secret.
+fn save_site(app_handle: &AppHandle, vault: &Vault, mut site: SiteProfile) -> Result<SiteProfile, String> {
+    let secrets = take_secrets(&mut site);
+    if secrets.is_some() && !vault.is_unlocked(app_handle) {
+        return Err("Unlock the credential vault to save passwords".to_string());
+    }
+
+    let saved = sites::upsert_site(app_handle, site)?;
+    if let Some(secrets) = secrets {
+        let existing = vault.get(app_handle, &saved.id)?.unwrap_or_default();
+        let merge = |new: Option<String>, old: Option<String>| match new {
+            Some(value) if value.is_empty() => None,
+            Some(value) => Some(value),
+            None => old,
+        };
+        // Only hops still in the chain keep their secrets.
+        let jump_hosts = saved.options.jump_hosts.iter()
+            .map(|hop| {
+                let new = secrets.jump_host(hop).cloned().unwrap_or_default();
+                let old = existing.jump_host(hop).cloned().unwrap_or_default();
+                JumpHostSecrets::new(hop, merge(new.password, old.password), merge(new.key_passphrase, old.key_passphrase))
+            })
+            .filter(|secrets| !secrets.is_empty())
+            .collect();
+        vault.set(app_handle, &saved.id, VaultEntry {
+            password: merge(secrets.password, existing.password),
+            key_passphrase: merge(secrets.key_passphrase, existing.key_passphrase),
+            proxy_password: merge(secrets.proxy_password, existing.proxy_password),
+            jump_hosts,
+        })?;
+    }
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn list_sites(app_handle: AppHandle) -> Result<CommandResult<Vec<SiteProfile>>, String> {
+    to_result(sites::load_sites(&app_handle))
+}
+
+#[tauri::command]
+pub fn create_site(app_handle: AppHandle, state: State<'_, FtpState>, mut site: SiteProfile) -> Result<CommandResult<SiteProfile>, String> {
+    site.id = String::new();
+    to_result(save_site(&app_handle, &state.vault, site))
+}
+
+#[tauri::command]
+pub fn update_site(app_handle: AppHandle, state: State<'_, FtpState>, site: SiteProfile) -> Result<CommandResult<SiteProfile>, String> {
+    if site.id.is_empty() {
+        return to_result(Err("Site id is required for update".to_string()));
+    }
+    to_result(save_site(&app_handle, &state.vault, site))
+}
+
+#[tauri::command]
+pub fn delete_site(app_handle: AppHandle, state: State<'_, FtpState>, site_id: String) -> Result<CommandResult<()>, String> {
+    if let Err(e) = sites::delete_site(&app_handle, &site_id) {
+        return to_result(Err(e));
+    }
+    if state.vault.is_unlocked(&app_handle) {
+        let _ = state.vault.remove(&app_handle, &site_id);
+    }
+    to_result(Ok(()))
+}
+
+#[tauri::command]
+pub fn list_site_groups(app_handle: AppHandle) -> Result<CommandResult<Vec<String>>, String> {
+    to_result(sites::list_groups(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_site_group(app_handle: AppHandle, site_id: String, group: Option<String>) -> Result<CommandResult<()>, String> {
+    to_result(sites::set_site_group(&app_handle, &site_id, group))
+}
+
+#[tauri::command]
+pub async fn connect_site(
+    app_handle: AppHandle,
+    state: State<'_, FtpState>,
+    site_id: String,
+    password: Option<String>,
+) -> Result<CommandResult<SiteConnection>, String> {
+    let site = match sites::find_site(&app_handle, &site_id) {
+        Ok(site) => site,
+        Err(e) => return to_result(Err(e)),
+    };
+    let secrets = match state.vault.get(&app_handle, &site.id) {
+        Ok(entry) => entry,
+        Err(e) if password.is_none() && site.auth_method.uses_secret() && state.vault.exists(&app_handle) => {
+            return to_result(Err(format!("{}. Unlock it to connect to {}", e, site.name)));
+        }
+        Err(_) => None,
+    };
+    let conn_info = match sites::connection_info(&site, password, secrets) {
+        Ok(conn_info) => conn_info,
+        Err(e) => return to_result(Err(e)),
+    };
+
+    let opened = match site.protocol.clone() {
+        Some(protocol) => open_session(&state, conn_info).await
+            .map(|session| (session, format!("{:?} Connected to {}", protocol, site.name), None)),
+        None => open_auto_session(&state, conn_info).await
+            .map(|(session, detection)| {
+                let message = format!("{:?} Connected to {} (auto-detected from {})", detection.protocol, site.name, detection.reason);
+                (session, message, Some(detection))
+            }),
+    };
+
+    match opened {
+        Ok((session, message, detected)) => {
+            if let Some(remote_dir) = site.remote_dir.as_ref().filter(|d| !d.is_empty()) {
+                if let Err(e) = enter_remote_dir(&state, &session, &normalize_remote_path(remote_dir)).await {
+                    return to_result(Err(e));
+                }
+            }
+            let session_id = register_session(&state, session).await;
+            to_result(Ok(SiteConnection {
+                session: ConnectedSession { session_id, message, detected },
+                remote_dir: site.remote_dir,
+                local_dir: site.local_dir,
+            }))
+        }
+        Err(e) => to_result(Err(format!("Failed to connect to {}: {}", site.name, e))),
+    }
+}
+
+fn default_import_path(candidates: &[&str]) -> Result<std::path::PathBuf, String> {
+    let config_dir = dirs::config_dir().ok_or("Could not determine the configuration directory")?;
+    candidates.iter()
+        .map(|name| config_dir.join(name))
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("No file found at {}", config_dir.join(candidates[0]).display()))
+}
+
+/// Saves every parsed profile. Passwords are kept only when the vault is unlocked; otherwise they are
+/// dropped and reported so the user can re-enter them.
+fn save_imported(app_handle: &AppHandle, vault: &Vault, parsed: ImportReport) -> ImportReport {
+    let mut report = ImportReport { sites: Vec::new(), issues: parsed.issues };
+    for mut site in parsed.sites {
+        if !vault.is_unlocked(app_handle) && take_secrets(&mut site).is_some() {
+            report.issues.push(site_import::ImportIssue {
+                name: site.name.clone(),
+                reason: "Credential vault is locked; the password was not imported".to_string(),
+            });
+        }
+        let name = site.name.clone();
+        match save_site(app_handle, vault, site) {
+            Ok(saved) => report.sites.push(saved),
+            Err(e) => report.issues.push(site_import::ImportIssue { name, reason: e }),
+        }
+    }
+    report
+}
+
+#[tauri::command]
+pub fn import_filezilla_sites(app_handle: AppHandle, state: State<'_, FtpState>, path: Option<String>) -> Result<CommandResult<ImportReport>, String> {
+    let path = match path {
+        Some(path) => Ok(std::path::PathBuf::from(path)),
+        None => default_import_path(&["filezilla/sitemanager.xml", "FileZilla/sitemanager.xml"]),
+    };
+    let parsed = path
+        .and_then(|path| std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e)))
+        .and_then(|xml| site_import::parse_filezilla(&xml));
+    to_result(parsed.map(|parsed| save_imported(&app_handle, &state.vault, parsed)))
+}
+
+#[tauri::command]
+pub fn import_winscp_sites(app_handle: AppHandle, state: State<'_, FtpState>, path: Option<String>) -> Result<CommandResult<ImportReport>, String> {
+    let path = match path {
+        Some(path) => Ok(std::path::PathBuf::from(path)),
+        None => default_import_path(&["WinSCP.ini", "winscp.ini"]),
+    };
+    let parsed = path
+        .and_then(|path| std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e)))
+        .map(|bytes| site_import::parse_winscp(&String::from_utf8_lossy(&bytes)));
+    to_result(parsed.map(|parsed| save_imported(&app_handle, &state.vault, parsed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site_with_secrets() -> SiteProfile {
+        serde_json::from_value(serde_json::json!({
+            "name": "Behind bastions",
+            "host": "internal.example.com",
+            "port": 22,
+            "username": "deploy",
+            "password": "site-pass",
+            "protocol": "SFTP",
+            "options": {
+                "proxy": { "kind": "socks5", "host": "proxy.example.com", "port": 1080, "username": "p", "password": "proxy-pass" },
+                "jump_hosts": [
+                    { "host": "bastion1.example.com", "username": "jump", "password": "hop1-pass" },
+                    { "host": "bastion2.example.com", "port": 2222, "username": "jump", "key_passphrase": "hop2-phrase" }
+                ]
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn secrets_are_stripped_from_the_profile() {
+        let mut site = site_with_secrets();
+        let secrets = take_secrets(&mut site).unwrap();
+
+        let json = serde_json::to_string(&site).unwrap();
+        for secret in ["site-pass", "proxy-pass", "hop1-pass", "hop2-phrase"] {
+            assert!(!json.contains(secret), "{} written to the site list: {}", secret, json);
+        }
+        assert_eq!(secrets.proxy_password.as_deref(), Some("proxy-pass"));
+        assert_eq!(secrets.jump_hosts.len(), 2);
+    }
+
+    #[test]
+    fn secrets_are_restored_on_connect() {
+        let mut site = site_with_secrets();
+        let secrets = take_secrets(&mut site);
+        // A reordered chain still gets each hop's own secrets.
+        site.options.jump_hosts.reverse();
+
+        let info = sites::connection_info(&site, None, secrets).unwrap();
+        assert_eq!(info.password, "site-pass");
+        assert_eq!(info.options.proxy.unwrap().password.as_deref(), Some("proxy-pass"));
+        let hops = &info.options.jump_hosts;
+        assert_eq!(hops[0].host, "bastion2.example.com");
+        assert_eq!(hops[0].key_passphrase.as_deref(), Some("hop2-phrase"));
+        assert_eq!(hops[0].password, None);
+        assert_eq!(hops[1].password.as_deref(), Some("hop1-pass"));
+    }
+}
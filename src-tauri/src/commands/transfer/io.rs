@@ -1,71 +1,29 @@
 use tauri::State;
-use futures_lite::io::Cursor as AsyncCursor;
-use futures_lite::io::AsyncReadExt;
 
-use crate::models::{FtpState, CommandResult, ConnectionProtocol};
-use crate::commands::common::{normalize_remote_path, get_or_reconnect_stream, get_or_create_sftp_client};
+use crate::models::{FtpState, CommandResult};
+use crate::commands::common::{normalize_remote_path, with_backend, with_backend_retrying};
 
 #[tauri::command]
 pub async fn read_text_file(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     is_remote: bool,
 ) -> Result<CommandResult<String>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_path = normalize_remote_path(&path);
-        
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
+        let result = with_backend_retrying(&session, |backend| {
+            let path = normalized_path.clone();
+            Box::pin(async move { backend.read(&path).await })
+        }).await;
 
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                match get_or_create_sftp_client(&state).await {
-                    Ok(sftp_client) => {
-                        let temp_dir = std::env::temp_dir();
-                        let temp_file_path = temp_dir.join(format!("bestftp_read_{}.tmp", uuid::Uuid::new_v4()));
-                        let temp_file_str = temp_file_path.to_string_lossy().to_string();
-
-                        match sftp_client.download_file(&normalized_path, &temp_file_str) {
-                            Ok(_) => {
-                                let content = std::fs::read_to_string(&temp_file_path)
-                                    .map_err(|e| format!("Failed to read temporary file: {}", e))?;
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: true, data: Some(content), error: None })
-                            },
-                            Err(e) => {
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP read failed: {}", e)) })
-                            }
-                        }
-                    },
-                    Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP connection failed: {}", e)) })
-                }
-            },
-            _ => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult { success: false, data: None, error: Some(e) });
-                }
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    match stream.retr_as_stream(&normalized_path).await {
-                        Ok(mut data_stream) => {
-                            let mut buffer = Vec::new();
-                            if let Err(e) = data_stream.read_to_end(&mut buffer).await {
-                                let _ = stream.finalize_retr_stream(data_stream).await;
-                                return Ok(CommandResult { success: false, data: None, error: Some(format!("Failed to read stream: {}", e)) });
-                            }
-                            let _ = stream.finalize_retr_stream(data_stream).await;
-                            
-                            let content = String::from_utf8_lossy(&buffer).to_string();
-                            Ok(CommandResult { success: true, data: Some(content), error: None })
-                        },
-                        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("FTP retr failed: {}", e)) })
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        match result {
+            Ok(data) => Ok(CommandResult { success: true, data: Some(String::from_utf8_lossy(&data).to_string()), error: None }),
+            Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("Remote read failed: {}", e)) }),
         }
     } else {
         match std::fs::read_to_string(path) {
@@ -78,61 +36,26 @@ pub async fn read_text_file(
 #[tauri::command]
 pub async fn write_text_file(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     content: String,
     is_remote: bool,
 ) -> Result<CommandResult<()>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_path = normalize_remote_path(&path);
-        
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                match get_or_create_sftp_client(&state).await {
-                    Ok(sftp_client) => {
-                        let temp_dir = std::env::temp_dir();
-                        let temp_file_path = temp_dir.join(format!("bestftp_write_{}.tmp", uuid::Uuid::new_v4()));
-                        let temp_file_str = temp_file_path.to_string_lossy().to_string();
-
-                        std::fs::write(&temp_file_path, content)
-                            .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+        let data = content.into_bytes();
+        let result = with_backend(&session, |backend| {
+            let (path, data) = (normalized_path.clone(), data.clone());
+            Box::pin(async move { backend.write(&path, data).await })
+        }).await;
 
-                        match sftp_client.upload_file(&temp_file_str, &normalized_path) {
-                            Ok(_) => {
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: true, data: None, error: None })
-                            },
-                            Err(e) => {
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP write failed: {}", e)) })
-                            }
-                        }
-                    },
-                    Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP connection failed: {}", e)) })
-                }
-            },
-            _ => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult {
-                        success: false,
-                        data: None,
-                        error: Some(e),
-                    });
-                }
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    let mut cursor = AsyncCursor::new(content.into_bytes());
-                    match stream.put_file(&normalized_path, &mut cursor).await {
-                        Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
-                        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e.to_string()) }),
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        match result {
+            Ok(_) => Ok(CommandResult { success: true, data: None, error: None }),
+            Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("Remote write failed: {}", e)) }),
         }
     } else {
         match std::fs::write(path, content) {
@@ -145,63 +68,24 @@ pub async fn write_text_file(
 #[tauri::command]
 pub async fn read_binary_file(
     state: State<'_, FtpState>,
+    session_id: Option<String>,
     path: String,
     is_remote: bool,
 ) -> Result<CommandResult<Vec<u8>>, String> {
     if is_remote {
+        let session = match state.remote_session(session_id.as_deref()).await {
+            Ok(session) => session,
+            Err(e) => return Ok(CommandResult { success: false, data: None, error: Some(e) }),
+        };
         let normalized_path = normalize_remote_path(&path);
-        
-        let conn_info_guard = state.connection_info.lock().await;
-        let protocol = conn_info_guard.as_ref().map(|c| c.protocol.clone());
-        drop(conn_info_guard);
-
-        match protocol {
-            Some(ConnectionProtocol::SFTP) => {
-                match get_or_create_sftp_client(&state).await {
-                    Ok(sftp_client) => {
-                        let temp_dir = std::env::temp_dir();
-                        let temp_file_path = temp_dir.join(format!("bestftp_read_bin_{}.tmp", uuid::Uuid::new_v4()));
-                        let temp_file_str = temp_file_path.to_string_lossy().to_string();
+        let result = with_backend_retrying(&session, |backend| {
+            let path = normalized_path.clone();
+            Box::pin(async move { backend.read(&path).await })
+        }).await;
 
-                        match sftp_client.download_file(&normalized_path, &temp_file_str) {
-                            Ok(_) => {
-                                let data = std::fs::read(&temp_file_path)
-                                    .map_err(|e| format!("Failed to read temporary file: {}", e))?;
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: true, data: Some(data), error: None })
-                            },
-                            Err(e) => {
-                                let _ = std::fs::remove_file(temp_file_path);
-                                Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP read failed: {}", e)) })
-                            }
-                        }
-                    },
-                    Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("SFTP connection failed: {}", e)) })
-                }
-            },
-            _ => {
-                if let Err(e) = get_or_reconnect_stream(&state).await {
-                    return Ok(CommandResult { success: false, data: None, error: Some(e) });
-                }
-                let mut client_guard = state.ftp_client.lock().await;
-                if let Some(stream) = client_guard.as_mut() {
-                    match stream.retr_as_stream(&normalized_path).await {
-                        Ok(mut data_stream) => {
-                            let mut buffer = Vec::new();
-                            if let Err(e) = data_stream.read_to_end(&mut buffer).await {
-                                let _ = stream.finalize_retr_stream(data_stream).await;
-                                return Ok(CommandResult { success: false, data: None, error: Some(format!("Failed to read stream: {}", e)) });
-                            }
-                            let _ = stream.finalize_retr_stream(data_stream).await;
-                            
-                            Ok(CommandResult { success: true, data: Some(buffer), error: None })
-                        },
-                        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e.to_string()) }),
-                    }
-                } else {
-                    Ok(CommandResult { success: false, data: None, error: Some("Not connected".to_string()) })
-                }
-            }
+        match result {
+            Ok(data) => Ok(CommandResult { success: true, data: Some(data), error: None }),
+            Err(e) => Ok(CommandResult { success: false, data: None, error: Some(format!("Remote read failed: {}", e)) }),
         }
     } else {
         match std::fs::read(path) {
@@ -0,0 +1,27 @@
+use tauri::{AppHandle, State};
+use crate::models::{FtpState, CommandResult};
+use crate::vault::VaultStatus;
+
+#[tauri::command]
+pub fn vault_status(app_handle: AppHandle, state: State<'_, FtpState>) -> Result<CommandResult<VaultStatus>, String> {
+    Ok(CommandResult { success: true, data: Some(state.vault.status(&app_handle)), error: None })
+}
+
+#[tauri::command]
+pub async fn unlock_vault(app_handle: AppHandle, state: State<'_, FtpState>, master_password: String) -> Result<CommandResult<()>, String> {
+    let vault = state.vault.clone();
+    let result = tokio::task::spawn_blocking(move || vault.unlock(&app_handle, &master_password))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(()) => Ok(CommandResult { success: true, data: None, error: None }),
+        Err(e) => Ok(CommandResult { success: false, data: None, error: Some(e) }),
+    }
+}
+
+#[tauri::command]
+pub fn lock_vault(state: State<'_, FtpState>) -> Result<CommandResult<()>, String> {
+    state.vault.lock();
+    Ok(CommandResult { success: true, data: None, error: None })
+}
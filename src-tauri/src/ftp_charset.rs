@@ -0,0 +1,181 @@
+//! Legacy filename encodings for FTP.
+//!
+//! suppaftp writes every command as UTF-8 and keeps its TLS stream private, so connections with a
+//! legacy charset talk to a loopback relay instead of the server. The relay encodes each command
+//! line on the way out and decodes each reply line on the way back, which covers the commands
+//! suppaftp builds itself (CWD, SIZE, MDTM, ...) and works the same over plain FTP and FTPS.
+
+use std::borrow::Cow;
+use async_std::net::{TcpListener, TcpStream};
+use encoding_rs::{Encoding, UTF_8};
+use futures_lite::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Looks up a WHATWG encoding label. Automatic detection and UTF-8 give `None`, since suppaftp
+/// already speaks UTF-8.
+pub fn charset_for_label(label: Option<&str>) -> Result<Option<&'static Encoding>, String> {
+    let Some(label) = label.map(str::trim).filter(|label| !label.is_empty() && !is_auto(label)) else {
+        return Ok(None);
+    };
+    let charset = Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("Unknown filename encoding: {}", label))?;
+    if charset == UTF_8 {
+        return Ok(None);
+    }
+    // FTP commands are ASCII, so UTF-16 and the ISO-2022 family cannot carry them.
+    if !charset.is_ascii_compatible() {
+        return Err(format!("Filename encoding {} cannot be used for FTP", label));
+    }
+    Ok(Some(charset))
+}
+
+/// Whether the label asks for UTF-8 when the server offers it.
+pub fn is_auto(label: &str) -> bool {
+    label.trim().is_empty() || label.trim().eq_ignore_ascii_case("auto")
+}
+
+pub fn decode(charset: &'static Encoding, bytes: &[u8]) -> String {
+    charset.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encodes one command line, or `None` when a character has no equivalent in `charset`.
+fn encode_line(charset: &'static Encoding, line: &str) -> Option<Vec<u8>> {
+    let (bytes, _, had_errors) = charset.encode(line);
+    (!had_errors).then(|| bytes.into_owned())
+}
+
+/// Connects a loopback socket to a relay in front of `server` and returns the client end, to be
+/// handed to suppaftp. A `greeting` already read from the server is replayed to the client first.
+pub async fn relay<S>(server: S, charset: &'static Encoding, greeting: Option<Vec<u8>>) -> std::io::Result<TcpStream>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let client = TcpStream::connect(listener.local_addr()?).await?;
+    let client_addr = client.local_addr()?;
+    // Anything else on the machine could dial the port first; only our own socket is relayed.
+    let inner = loop {
+        let (inner, peer) = listener.accept().await?;
+        if peer == client_addr {
+            break inner;
+        }
+    };
+
+    let (server_read, server_write) = futures_lite::io::split(server);
+    let mut replies = inner.clone();
+    if let Some(greeting) = greeting {
+        replies.write_all(decode(charset, &greeting).as_bytes()).await?;
+    }
+    // Once either side hangs up both sockets are dropped, which is what closes them: closing a TCP
+    // write half only flushes it.
+    async_std::task::spawn(futures_lite::future::or(
+        pump_commands(inner.clone(), server_write, inner, charset),
+        pump_replies(server_read, replies, charset),
+    ));
+    Ok(client)
+}
+
+async fn pump_commands<W: AsyncWrite + Unpin>(client: TcpStream, mut server: W, mut replies: TcpStream, charset: &'static Encoding) {
+    let mut commands = BufReader::new(client);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match commands.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        match encode_line(charset, &String::from_utf8_lossy(&line)) {
+            Some(encoded) => {
+                if server.write_all(&encoded).await.is_err() || server.flush().await.is_err() {
+                    break;
+                }
+            }
+            // Answer for the server rather than send a mangled path it might act on.
+            None => {
+                let reply = format!("553 The name cannot be written in {}\r\n", charset.name());
+                if replies.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = server.close().await;
+}
+
+async fn pump_replies<R: AsyncRead + Unpin>(server: R, mut client: TcpStream, charset: &'static Encoding) {
+    let mut replies = BufReader::new(server);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match replies.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let decoded: Cow<str> = charset.decode_without_bom_handling(&line).0;
+        if client.write_all(decoded.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::net::Shutdown;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1251};
+
+    #[test]
+    fn labels() {
+        assert_eq!(charset_for_label(None).unwrap(), None);
+        assert_eq!(charset_for_label(Some(" Auto ")).unwrap(), None);
+        assert_eq!(charset_for_label(Some("utf8")).unwrap(), None);
+        assert_eq!(charset_for_label(Some("cp1251")).unwrap(), Some(WINDOWS_1251));
+        assert_eq!(charset_for_label(Some("sjis")).unwrap(), Some(SHIFT_JIS));
+        assert!(charset_for_label(Some("utf-16le")).is_err());
+        assert!(charset_for_label(Some("no-such-charset")).is_err());
+    }
+
+    #[test]
+    fn lines_are_transcoded() {
+        assert_eq!(encode_line(WINDOWS_1251, "CWD Папка\r\n").unwrap(), b"CWD \xcf\xe0\xef\xea\xe0\r\n");
+        assert_eq!(decode(WINDOWS_1251, b"257 \"/\xcf\xe0\xef\xea\xe0\"\r\n"), "257 \"/Папка\"\r\n");
+        assert_eq!(encode_line(SHIFT_JIS, "SIZE 日本.txt\r\n").unwrap(), b"SIZE \x93\xfa\x96\x7b.txt\r\n");
+        assert_eq!(encode_line(WINDOWS_1251, "DELE 日本.txt\r\n"), None);
+    }
+
+    #[test]
+    fn relay_round_trip() {
+        async_std::task::block_on(async {
+            let server_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let to_server = TcpStream::connect(server_listener.local_addr().unwrap()).await.unwrap();
+            let (server, _) = server_listener.accept().await.unwrap();
+
+            let client = relay(to_server, WINDOWS_1251, Some(b"220 \xcf\xf0\xe8\xe2\xe5\xf2\r\n".to_vec())).await.unwrap();
+            let mut client_lines = BufReader::new(client.clone());
+            let mut server_lines = BufReader::new(server.clone());
+            let mut line = String::new();
+            client_lines.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "220 Привет\r\n");
+
+            (&client).write_all("MKD Папка\r\n".as_bytes()).await.unwrap();
+            let mut raw = Vec::new();
+            server_lines.read_until(b'\n', &mut raw).await.unwrap();
+            assert_eq!(raw, b"MKD \xcf\xe0\xef\xea\xe0\r\n");
+            (&server).write_all(b"257 \"/\xcf\xe0\xef\xea\xe0\" created\r\n").await.unwrap();
+            line.clear();
+            client_lines.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "257 \"/Папка\" created\r\n");
+
+            (&client).write_all("MKD 日本\r\n".as_bytes()).await.unwrap();
+            line.clear();
+            client_lines.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with("553 "), "{}", line);
+
+            // Hanging up on either side closes the other.
+            client.shutdown(Shutdown::Write).unwrap();
+            raw.clear();
+            assert_eq!(server_lines.read_until(b'\n', &mut raw).await.unwrap(), 0);
+            line.clear();
+            assert_eq!(client_lines.read_line(&mut line).await.unwrap(), 0);
+        });
+    }
+}
@@ -0,0 +1,613 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use async_std::io::{Read, Write};
+use async_std::net::{TcpListener, TcpStream};
+use encoding_rs::Encoding;
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+use suppaftp::{AsyncNativeTlsConnector, AsyncNativeTlsFtpStream, FtpError, FtpResult, Status};
+use suppaftp::async_native_tls::{TlsConnector, TlsStream};
+use suppaftp::types::Response;
+use crate::ftp_charset::{self, charset_for_label, is_auto};
+use crate::models::{ConnectionInfo, ConnectionProtocol, FtpDataMode, PortRange};
+use crate::net::{normalize_host, resolve};
+use crate::proxy::{self, ProxySettings};
+use crate::session_log::{trace_error, trace_info, trace_received, trace_sent};
+use crate::utils::ListFormat;
+
+const ACTIVE_ACCEPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An FTP control connection plus the data-channel settings it was opened with.
+///
+/// Commands go through `command`, which records them and their replies on the session transcript,
+/// and data connections are opened here rather than by suppaftp, so that transfers are recorded
+/// too and work through the `ftp_charset` relay, where suppaftp only sees a loopback socket.
+pub struct FtpConnection {
+    stream: AsyncNativeTlsFtpStream,
+    active: bool,
+    port_range: Option<PortRange>,
+    /// Whether FEAT advertised MLST; older servers only answer LIST.
+    mlsd_supported: bool,
+    /// Encoding of paths and listings on servers that predate UTF-8; `None` leaves both to suppaftp.
+    charset: Option<&'static Encoding>,
+    /// The server name to verify when data connections opened here need TLS.
+    data_tls: Option<String>,
+    /// Our address on the real control connection, advertised in PORT and EPRT.
+    local_ip: IpAddr,
+    passive_route: PassiveRoute,
+    /// How long a reply, or a stalled data connection, is waited for.
+    read_timeout: Duration,
+}
+
+/// Where passive data connections go.
+struct PassiveRoute {
+    proxy: Option<ProxySettings>,
+    control_host: String,
+    epsv: bool,
+    timeout: Duration,
+}
+
+/// A data connection opened here, encrypted when the control connection is. Reads and writes
+/// fail with `TimedOut` once the connection makes no progress for `timeout`.
+struct DataStream {
+    socket: DataSocket,
+    timeout: Duration,
+    stall: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+enum DataSocket {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl DataStream {
+    fn new(socket: DataSocket, timeout: Duration) -> Self {
+        DataStream { socket, timeout, stall: None }
+    }
+
+    /// Passes `poll` through, starting the stall timer while it is pending and failing once the
+    /// timer runs out.
+    fn deadline<T>(&mut self, cx: &mut Context<'_>, poll: Poll<std::io::Result<T>>) -> Poll<std::io::Result<T>> {
+        if poll.is_ready() {
+            self.stall = None;
+            return poll;
+        }
+        let timeout = self.timeout;
+        let stall = self.stall.get_or_insert_with(|| Box::pin(async_std::task::sleep(timeout)));
+        match stall.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.stall = None;
+                Poll::Ready(Err(timed_out("The data connection stalled")))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Read for DataStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = match &mut this.socket {
+            DataSocket::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            DataSocket::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        };
+        this.deadline(cx, poll)
+    }
+}
+
+impl Write for DataStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = match &mut this.socket {
+            DataSocket::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            DataSocket::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        };
+        this.deadline(cx, poll)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let poll = match &mut this.socket {
+            DataSocket::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            DataSocket::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        };
+        this.deadline(cx, poll)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let poll = match &mut this.socket {
+            DataSocket::Plain(stream) => Pin::new(stream).poll_close(cx),
+            DataSocket::Tls(stream) => Pin::new(stream.as_mut()).poll_close(cx),
+        };
+        this.deadline(cx, poll)
+    }
+}
+
+impl FtpConnection {
+    /// Sends `line` and reads the reply, recording both on the session transcript.
+    pub async fn command(&mut self, line: impl Into<String>, expected: &[Status]) -> FtpResult<Response> {
+        send(&mut self.stream, line, expected, self.read_timeout).await
+    }
+
+    pub async fn noop(&mut self) -> FtpResult<()> {
+        self.command("NOOP", &[Status::CommandOk]).await.map(|_| ())
+    }
+
+    pub async fn quit(&mut self) -> FtpResult<()> {
+        self.command("QUIT", &[Status::Closing]).await.map(|_| ())
+    }
+
+    pub async fn retr_as_stream(&mut self, path: &str) -> FtpResult<Box<dyn Read + Send + Unpin>> {
+        let data_stream = self.data_stream("RETR", path).await?;
+        Ok(Box::new(data_stream))
+    }
+
+    /// Closes a download's data connection and reads the server's verdict on the transfer.
+    pub async fn finalize_retr_stream(&mut self, data_stream: impl Read) -> FtpResult<()> {
+        drop(data_stream);
+        self.transfer_complete().await
+    }
+
+    pub async fn put_file<R: Read + Unpin>(&mut self, path: &str, reader: &mut R) -> FtpResult<u64> {
+        let mut data_stream = self.data_stream("STOR", path).await?;
+        let bytes = async_std::io::copy(reader, &mut data_stream).await
+            .map_err(FtpError::ConnectionError)?;
+        data_stream.close().await.map_err(FtpError::ConnectionError)?;
+        drop(data_stream);
+        self.transfer_complete().await?;
+        Ok(bytes)
+    }
+
+    async fn transfer_complete(&mut self) -> FtpResult<()> {
+        receive(&mut self.stream, &[Status::ClosingDataConnection, Status::RequestedFileActionOk], self.read_timeout).await.map(|_| ())
+    }
+
+    /// How `list_lines` lists directories: with MLSD when the server supports it, LIST otherwise.
+    pub fn list_format(&self) -> ListFormat {
+        if self.mlsd_supported { ListFormat::Mlsd } else { ListFormat::List }
+    }
+
+    /// Lists `path` in the `list_format`, for `utils::parse_ftp_list_line`.
+    pub async fn list_lines(&mut self, path: &str) -> FtpResult<Vec<String>> {
+        let command = match self.list_format() {
+            ListFormat::Mlsd => "MLSD",
+            ListFormat::List => "LIST",
+        };
+        let mut data_stream = self.data_stream(command, path).await?;
+        let mut listing = Vec::new();
+        data_stream.read_to_end(&mut listing).await.map_err(FtpError::ConnectionError)?;
+        drop(data_stream);
+        self.transfer_complete().await?;
+
+        Ok(listing.split(|&byte| byte == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .map(|line| self.decode(line))
+            .collect())
+    }
+
+    pub async fn mkdir(&mut self, path: &str) -> FtpResult<()> {
+        self.command(format!("MKD {}", path), &[Status::PathCreated]).await.map(|_| ())
+    }
+
+    pub async fn rm(&mut self, path: &str) -> FtpResult<()> {
+        self.command(format!("DELE {}", path), &[Status::RequestedFileActionOk]).await.map(|_| ())
+    }
+
+    pub async fn rmdir(&mut self, path: &str) -> FtpResult<()> {
+        self.command(format!("RMD {}", path), &[Status::RequestedFileActionOk]).await.map(|_| ())
+    }
+
+    pub async fn rename(&mut self, from: &str, to: &str) -> FtpResult<()> {
+        self.command(format!("RNFR {}", from), &[Status::RequestFilePending]).await?;
+        self.command(format!("RNTO {}", to), &[Status::RequestedFileActionOk]).await.map(|_| ())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        match self.charset {
+            Some(charset) => ftp_charset::decode(charset, bytes),
+            None => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+
+    /// Opens a data connection for `verb path`, wrapped in TLS on FTPS.
+    async fn data_stream(&mut self, verb: &str, path: &str) -> FtpResult<DataStream> {
+        let tcp = self.data_connection(verb, path).await?;
+        let socket = match &self.data_tls {
+            None => DataSocket::Plain(tcp),
+            Some(domain) => match async_std::future::timeout(self.read_timeout, TlsConnector::new().connect(domain, tcp)).await {
+                Ok(Ok(tls)) => DataSocket::Tls(Box::new(tls)),
+                Ok(Err(e)) => return Err(FtpError::SecureError(e.to_string())),
+                Err(_) => return Err(FtpError::ConnectionError(timed_out("The data connection TLS handshake timed out"))),
+            },
+        };
+        Ok(DataStream::new(socket, self.read_timeout))
+    }
+
+    /// Opens a TCP data connection and sends `verb path` for it, in active mode or passive mode.
+    async fn data_connection(&mut self, verb: &str, path: &str) -> FtpResult<TcpStream> {
+        let command = format!("{} {}", verb, path);
+        let expected = [Status::AboutToSend, Status::AlreadyOpen];
+        if !self.active {
+            let data_stream = self.passive_data_stream().await?;
+            self.command(command, &expected).await?;
+            return Ok(data_stream);
+        }
+
+        let listener = bind_active_listener(self.local_ip, self.port_range.as_ref()).await?;
+        let port = listener.local_addr().map_err(FtpError::ConnectionError)?.port();
+
+        let port_command = match self.local_ip {
+            IpAddr::V4(ip) => format!("PORT {},{},{}", ip.to_string().replace('.', ","), port / 256, port % 256),
+            IpAddr::V6(ip) => format!("EPRT |2|{}|{}|", ip, port),
+        };
+        self.command(port_command, &[Status::CommandOk]).await?;
+        self.command(command, &expected).await?;
+
+        match async_std::future::timeout(ACTIVE_ACCEPT_TIMEOUT, listener.accept()).await {
+            Ok(Ok((data_stream, _))) => Ok(data_stream),
+            Ok(Err(e)) => Err(FtpError::ConnectionError(e)),
+            Err(_) => Err(FtpError::ConnectionError(timed_out("Server did not open the active-mode data connection"))),
+        }
+    }
+
+    /// Opens a passive data connection, through the proxy if there is one. EPSV only carries a
+    /// port, so those connections go to the control host rather than the proxy we are connected to.
+    async fn passive_data_stream(&mut self) -> FtpResult<TcpStream> {
+        let (host, port) = if self.passive_route.epsv {
+            let response = self.command("EPSV", &[Status::ExtendedPassiveMode]).await?;
+            let port = String::from_utf8_lossy(&response.body).split('|').nth(3)
+                .and_then(|port| port.trim().parse().ok())
+                .ok_or(FtpError::UnexpectedResponse(response))?;
+            (self.passive_route.control_host.clone(), port)
+        } else {
+            let response = self.command("PASV", &[Status::PassiveMode]).await?;
+            parse_pasv_address(&String::from_utf8_lossy(&response.body))
+                .ok_or(FtpError::UnexpectedResponse(response))?
+        };
+
+        let route = &self.passive_route;
+        proxy::connect_tcp_async(route.proxy.clone(), host, port, route.timeout).await
+            .map_err(|e| FtpError::ConnectionError(std::io::Error::other(e)))
+    }
+}
+
+/// Sends `line` and reads the reply, recording both on the session transcript.
+async fn send(stream: &mut AsyncNativeTlsFtpStream, line: impl Into<String>, expected: &[Status], timeout: Duration) -> FtpResult<Response> {
+    let line = line.into();
+    trace_sent(&line);
+    let result = within(timeout, stream.custom_command(line, expected)).await;
+    trace_reply(&result);
+    result
+}
+
+/// Reads the reply to a command sent earlier, recording it on the session transcript.
+async fn receive(stream: &mut AsyncNativeTlsFtpStream, expected: &[Status], timeout: Duration) -> FtpResult<Response> {
+    let result = within(timeout, stream.read_response_in(expected)).await;
+    trace_reply(&result);
+    result
+}
+
+/// Gives up on an exchange with the server after `timeout`.
+async fn within<T>(timeout: Duration, exchange: impl Future<Output = FtpResult<T>>) -> FtpResult<T> {
+    async_std::future::timeout(timeout, exchange).await
+        .unwrap_or_else(|_| Err(FtpError::ConnectionError(timed_out("The server did not reply in time"))))
+}
+
+fn timed_out(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, message)
+}
+
+fn trace_reply(result: &FtpResult<Response>) {
+    match result {
+        Ok(response) | Err(FtpError::UnexpectedResponse(response)) => trace_received(&String::from_utf8_lossy(&response.body)),
+        Err(e) => trace_error(&e.to_string()),
+    }
+}
+
+/// Records `line` for an exchange that suppaftp carries out itself, where only a failure is seen.
+async fn traced<T>(line: &str, exchange: impl Future<Output = FtpResult<T>>) -> FtpResult<T> {
+    trace_sent(line);
+    let result = exchange.await;
+    if let Err(e) = &result {
+        trace_error(&e.to_string());
+    }
+    result
+}
+
+/// Reads `h1,h2,h3,h4,p1,p2` out of a PASV reply, with or without the customary parentheses.
+fn parse_pasv_address(reply: &str) -> Option<(String, u16)> {
+    let numbers = reply.split(|c: char| !c.is_ascii_digit() && c != ',')
+        .find(|part| part.matches(',').count() == 5)?
+        .split(',')
+        .map(|n| n.parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let host = format!("{}.{}.{}.{}", numbers[0], numbers[1], numbers[2], numbers[3]);
+    Some((host, u16::from(numbers[4]) * 256 + u16::from(numbers[5])))
+}
+
+async fn bind_active_listener(ip: IpAddr, range: Option<&PortRange>) -> FtpResult<TcpListener> {
+    let Some(range) = range else {
+        return TcpListener::bind(SocketAddr::new(ip, 0)).await.map_err(FtpError::ConnectionError);
+    };
+
+    let mut last_error = None;
+    for port in range.start..=range.end {
+        match TcpListener::bind(SocketAddr::new(ip, port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(FtpError::ConnectionError(last_error.unwrap_or_else(|| std::io::Error::new(
+        std::io::ErrorKind::AddrNotAvailable,
+        format!("Active mode port range {}-{} is empty", range.start, range.end),
+    ))))
+}
+
+pub fn tls_connector() -> AsyncNativeTlsConnector {
+    AsyncNativeTlsConnector::from(TlsConnector::new())
+}
+
+async fn connect_plain(conn_info: &ConnectionInfo) -> Result<AsyncNativeTlsFtpStream, String> {
+    let tcp = proxy::connect_tcp_async(conn_info.options.proxy.clone(), conn_info.host.clone(), conn_info.port, conn_info.options.connect_timeout()).await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    let stream = within(conn_info.options.read_timeout(), AsyncNativeTlsFtpStream::connect_with_stream(tcp)).await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    trace_welcome(&stream);
+    Ok(stream)
+}
+
+fn trace_welcome(stream: &AsyncNativeTlsFtpStream) {
+    if let Some(welcome) = stream.get_welcome_msg() {
+        trace_received(welcome);
+    }
+}
+
+pub async fn connect_ftp(conn_info: &ConnectionInfo) -> Result<FtpConnection, String> {
+    let options = &conn_info.options;
+    let host = normalize_host(&conn_info.host);
+    let secure = !matches!(conn_info.protocol, ConnectionProtocol::FTP);
+    if matches!(options.data_mode, FtpDataMode::Active) && options.proxy.is_some() {
+        return Err("Active mode cannot be used through a proxy".to_string());
+    }
+    let charset = charset_for_label(options.ftp_charset.as_deref())?;
+    let read_timeout = options.read_timeout();
+
+    let (mut stream, (local_addr, peer_addr)) = match charset {
+        Some(charset) => connect_transcoded(conn_info, &host, charset).await?,
+        None => {
+            let stream = match conn_info.protocol {
+                ConnectionProtocol::FTPSImplicit => connect_implicit_tls(conn_info).await?,
+                ConnectionProtocol::FTPS => {
+                    let stream = connect_plain(conn_info).await?;
+                    traced("AUTH TLS", within(read_timeout, stream.into_secure(tls_connector(), &host))).await
+                        .map_err(|e| format!("AUTH TLS failed: {}", e))?
+                }
+                _ => connect_plain(conn_info).await?,
+            };
+            let addrs = control_addrs(stream.get_ref())?;
+            (stream, addrs)
+        }
+    };
+
+    login(&mut stream, &conn_info.username, &conn_info.password, read_timeout).await
+        .map_err(|e| format!("Login failed: {}", e))?;
+
+    let _ = send(&mut stream, "TYPE I", &[Status::CommandOk], read_timeout).await;
+
+    // Servers that reject FEAT predate MLSD and UTF8 as well.
+    let features = traced("FEAT", within(read_timeout, stream.feat())).await.unwrap_or_default();
+    if !features.is_empty() {
+        trace_info(&format!("Server features: {}", features.keys().cloned().collect::<Vec<_>>().join(", ")));
+    }
+    let has_feature = |feature: &str| features.keys().any(|name| name.eq_ignore_ascii_case(feature));
+    let mlsd_supported = has_feature("MLST") || has_feature("MLSD");
+    // An explicit UTF-8 label needs nothing from us; without one, UTF-8 is switched on when offered.
+    if options.ftp_charset.as_deref().is_none_or(is_auto) && has_feature("UTF8") {
+        // Some servers answer 202 when UTF-8 is already on, which counts as unexpected here.
+        let _ = send(&mut stream, "OPTS UTF8 ON", &[Status::CommandOk], read_timeout).await;
+    }
+
+    // PASV can only describe IPv4 addresses, so IPv6 control connections always use EPSV.
+    let ipv6 = peer_addr.is_ipv6() || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6());
+    let epsv = matches!(options.data_mode, FtpDataMode::ExtendedPassive)
+        || (matches!(options.data_mode, FtpDataMode::Passive) && ipv6);
+
+    let data_tls = secure.then(|| host.clone());
+    let passive_route = PassiveRoute { proxy: options.proxy.clone(), control_host: host, epsv, timeout: options.connect_timeout() };
+    Ok(FtpConnection {
+        stream,
+        active: matches!(options.data_mode, FtpDataMode::Active),
+        port_range: options.active_port_range.clone(),
+        mlsd_supported,
+        charset,
+        data_tls,
+        local_ip: local_addr.ip(),
+        passive_route,
+        read_timeout,
+    })
+}
+
+/// Logs in, sending the password only if the server asks for one.
+async fn login(stream: &mut AsyncNativeTlsFtpStream, username: &str, password: &str, timeout: Duration) -> FtpResult<()> {
+    let response = send(stream, format!("USER {}", username), &[Status::LoggedIn, Status::NeedPassword], timeout).await?;
+    if response.status == Status::NeedPassword {
+        send(stream, format!("PASS {}", password), &[Status::LoggedIn], timeout).await?;
+    }
+    Ok(())
+}
+
+fn control_addrs(control: &TcpStream) -> Result<(SocketAddr, SocketAddr), String> {
+    control.local_addr()
+        .and_then(|local| Ok((local, control.peer_addr()?)))
+        .map_err(|e| format!("Connection failed: {}", e))
+}
+
+/// Connects through an `ftp_charset::relay`. suppaftp then only sees the loopback socket, so the
+/// TLS handshake happens here, and the addresses returned are those of the real control connection.
+async fn connect_transcoded(conn_info: &ConnectionInfo, host: &str, charset: &'static Encoding) -> Result<(AsyncNativeTlsFtpStream, (SocketAddr, SocketAddr)), String> {
+    let connection_failed = |e: std::io::Error| format!("Connection failed: {}", e);
+    let read_timeout = conn_info.options.read_timeout();
+    let mut tcp = proxy::connect_tcp_async(conn_info.options.proxy.clone(), conn_info.host.clone(), conn_info.port, conn_info.options.connect_timeout()).await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    let addrs = control_addrs(&tcp)?;
+
+    let client = match conn_info.protocol {
+        ConnectionProtocol::FTPS => {
+            let greeting = read_reply(&mut tcp, "220", read_timeout).await.map_err(connection_failed)?;
+            trace_received(&ftp_charset::decode(charset, &greeting));
+            trace_sent("AUTH TLS");
+            tcp.write_all(b"AUTH TLS\r\n").await.map_err(connection_failed)?;
+            let reply = read_reply(&mut tcp, "234", read_timeout).await.map_err(|e| format!("AUTH TLS failed: {}", e))?;
+            trace_received(&ftp_charset::decode(charset, &reply));
+            let tls = TlsConnector::new().connect(host, tcp).await
+                .map_err(|e| format!("AUTH TLS failed: {}", e))?;
+            ftp_charset::relay(tls, charset, Some(greeting)).await
+        }
+        ConnectionProtocol::FTPSImplicit => {
+            let tls = TlsConnector::new().connect(host, tcp).await
+                .map_err(|e| format!("Implicit TLS connection failed: {}", e))?;
+            ftp_charset::relay(tls, charset, None).await
+        }
+        _ => ftp_charset::relay(tcp, charset, None).await,
+    }.map_err(connection_failed)?;
+
+    let mut stream = within(read_timeout, AsyncNativeTlsFtpStream::connect_with_stream(client)).await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    // The explicit FTPS greeting was read, and recorded, before the handshake.
+    if !matches!(conn_info.protocol, ConnectionProtocol::FTPS) {
+        trace_welcome(&stream);
+    }
+    if !matches!(conn_info.protocol, ConnectionProtocol::FTP) {
+        protect_data(&mut stream, read_timeout).await?;
+    }
+    Ok((stream, addrs))
+}
+
+/// Reads one reply, multi-line or not, a byte at a time so that nothing after it (such as the
+/// start of a TLS handshake) is consumed. Fails unless the reply has the `expected` code and
+/// arrives within `timeout`.
+async fn read_reply<S: Read + Unpin>(stream: &mut S, expected: &str, timeout: Duration) -> std::io::Result<Vec<u8>> {
+    let reply = async_std::io::timeout(timeout, read_reply_lines(stream)).await?;
+    if reply.starts_with(expected.as_bytes()) {
+        Ok(reply)
+    } else {
+        Err(std::io::Error::other(format!("unexpected reply: {}", String::from_utf8_lossy(&reply).trim())))
+    }
+}
+
+async fn read_reply_lines<S: Read + Unpin>(stream: &mut S) -> std::io::Result<Vec<u8>> {
+    let mut reply = Vec::new();
+    let mut line_start = 0;
+    let mut byte = [0u8];
+    loop {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        reply.push(byte[0]);
+        if byte[0] != b'\n' {
+            continue;
+        }
+        // A reply ends with a line holding its code followed by a space.
+        let line = &reply[line_start..];
+        if line.len() >= 4 && line[3] == b' ' && line[..3] == reply[..3] && line[..3].iter().all(u8::is_ascii_digit) {
+            break;
+        }
+        line_start = reply.len();
+    }
+    Ok(reply)
+}
+
+/// Asks for encrypted data connections, which FTPS servers require before the first transfer.
+async fn protect_data(stream: &mut AsyncNativeTlsFtpStream, timeout: Duration) -> Result<(), String> {
+    send(stream, "PBSZ 0", &[Status::CommandOk], timeout).await
+        .map_err(|e| format!("PBSZ failed: {}", e))?;
+    send(stream, "PROT P", &[Status::CommandOk], timeout).await
+        .map_err(|e| format!("PROT P failed: {}", e))?;
+    Ok(())
+}
+
+async fn connect_implicit_tls(conn_info: &ConnectionInfo) -> Result<AsyncNativeTlsFtpStream, String> {
+    // suppaftp only dials implicit TLS by address, so a proxied tunnel is exposed on a loopback port first.
+    let proxy = conn_info.options.proxy.clone();
+    let (host, port) = (normalize_host(&conn_info.host), conn_info.port);
+    let timeout = conn_info.options.connect_timeout();
+    let domain = host.clone();
+    let addrs: Vec<SocketAddr> = tokio::task::spawn_blocking(move || match proxy {
+        Some(proxy) => proxy::connect_tcp(Some(&proxy), &host, port, timeout)
+            .and_then(|stream| proxy::forward_once(stream, timeout))
+            .map(|local_addr| vec![local_addr]),
+        None => resolve(&host, port),
+    })
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let read_timeout = conn_info.options.read_timeout();
+    let mut stream = within(read_timeout, AsyncNativeTlsFtpStream::connect_secure_implicit(&addrs[..], tls_connector(), &domain)).await
+        .map_err(|e| format!("Implicit TLS connection failed: {}", e))?;
+    trace_welcome(&stream);
+
+    protect_data(&mut stream, read_timeout).await?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(input: &[u8], expected: &str) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        async_std::task::block_on(async {
+            let mut input = futures_lite::io::Cursor::new(input.to_vec());
+            let reply = read_reply(&mut input, expected, Duration::from_secs(1)).await?;
+            let mut rest = Vec::new();
+            input.read_to_end(&mut rest).await?;
+            Ok((reply, rest))
+        })
+    }
+
+    #[test]
+    fn replies_are_read_up_to_their_last_line() {
+        let (greeting, rest) = reply(b"220-Welcome\r\n220-to the\r\n220 server\r\nTLS", "220").unwrap();
+        assert_eq!(greeting, b"220-Welcome\r\n220-to the\r\n220 server\r\n");
+        assert_eq!(rest, b"TLS");
+        // Continuation lines need not repeat the code.
+        let (greeting, _) = reply(b"220-Hello\r\n 220 is not the end\r\n220 Ready\r\n", "220").unwrap();
+        assert!(greeting.ends_with(b"220 Ready\r\n"));
+    }
+
+    #[test]
+    fn unexpected_replies_fail() {
+        assert!(reply(b"530 AUTH not understood\r\n", "234").is_err());
+        assert_eq!(reply(b"220-Hello\r\n", "220").unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn silent_servers_time_out() {
+        async_std::task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+            let (server, _) = listener.accept().await.unwrap();
+            let timeout = Duration::from_millis(50);
+            let error = read_reply(&mut client, "220", timeout).await.unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+
+            // Each read gets the full timeout; only a stall fails.
+            let mut data = DataStream::new(DataSocket::Plain(client), timeout);
+            (&server).write_all(b"abc").await.unwrap();
+            let mut buf = [0u8; 3];
+            data.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"abc");
+            assert_eq!(data.read(&mut buf).await.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        });
+    }
+
+    #[test]
+    fn pasv_addresses() {
+        assert_eq!(parse_pasv_address("Entering Passive Mode (192,168,1,2,19,137)"), Some(("192.168.1.2".to_string(), 5001)));
+        assert_eq!(parse_pasv_address("Entering Passive Mode 10,0,0,1,0,21"), Some(("10.0.0.1".to_string(), 21)));
+        assert_eq!(parse_pasv_address("Entering Passive Mode (300,0,0,1,0,21)"), None);
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, KnownHosts, Session};
+use tauri::{AppHandle, Manager};
+use crate::prompts::PendingPrompts;
+
+#[derive(Clone, serde::Serialize)]
+pub struct KnownHostEntry {
+    pub host: String,
+    pub key_type: String,
+    pub fingerprint: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct HostKeyPrompt {
+    request_id: String,
+    host: String,
+    port: u16,
+    key_type: String,
+    fingerprint: String,
+}
+
+pub fn known_hosts_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app.path().app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    Ok(config_dir.join("known_hosts"))
+}
+
+pub fn host_entry_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+pub fn fingerprint(raw_key: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(raw_key)))
+}
+
+fn key_type_name(key_type: HostKeyType) -> &'static str {
+    match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+fn key_type_from_blob(raw_key: &[u8]) -> String {
+    if raw_key.len() < 4 {
+        return "unknown".to_string();
+    }
+    let len = u32::from_be_bytes([raw_key[0], raw_key[1], raw_key[2], raw_key[3]]) as usize;
+    raw_key.get(4..4 + len)
+        .and_then(|name| std::str::from_utf8(name).ok())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+fn load_known_hosts(session: &Session, path: &Path) -> Result<KnownHosts, String> {
+    let mut known_hosts = session.known_hosts()
+        .map_err(|e| format!("Failed to initialize known hosts: {}", e))?;
+    if path.exists() {
+        known_hosts.read_file(path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| format!("Failed to read known hosts file: {}", e))?;
+    }
+    Ok(known_hosts)
+}
+
+pub fn verify_host_key(session: &Session, host: &str, port: u16, prompts: &PendingPrompts) -> Result<(), String> {
+    let app = prompts.app().ok_or("Host key verification is unavailable")?;
+    let path = known_hosts_path(app)?;
+    let (raw_key, host_key_type) = session.host_key().ok_or("Server did not provide a host key")?;
+    let key_type = key_type_name(host_key_type);
+    let offered = fingerprint(raw_key);
+    let mut known_hosts = load_known_hosts(session, &path)?;
+
+    match known_hosts.check_port(host, port, raw_key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => {
+            let entry_name = host_entry_name(host, port);
+            let expected = known_hosts.hosts().unwrap_or_default().iter()
+                .filter(|h| h.name() == Some(entry_name.as_str()))
+                .filter_map(|h| STANDARD.decode(h.key()).ok())
+                .map(|key| fingerprint(&key))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(format!(
+                "HOST KEY MISMATCH for {}: the server offered {} {} but the trusted key is {}. \
+                 The host may have been reinstalled, or someone may be intercepting the connection. \
+                 Remove the old key from known hosts only if you are sure the change is legitimate.",
+                entry_name, key_type, offered, expected
+            ))
+        }
+        CheckResult::NotFound => {
+            let request = HostKeyPrompt {
+                request_id: uuid::Uuid::new_v4().to_string(),
+                host: host.to_string(),
+                port,
+                key_type: key_type.to_string(),
+                fingerprint: offered.clone(),
+            };
+            if prompts.ask("host-key-prompt", &request.request_id.clone(), request).is_none() {
+                return Err(format!("Host key {} {} for {} was not trusted", key_type, offered, host_entry_name(host, port)));
+            }
+
+            known_hosts.add(&host_entry_name(host, port), raw_key, "added by BestFTP", host_key_type.into())
+                .map_err(|e| format!("Failed to add host key: {}", e))?;
+            known_hosts.write_file(&path, KnownHostFileKind::OpenSSH)
+                .map_err(|e| format!("Failed to save known hosts file: {}", e))?;
+            Ok(())
+        }
+        CheckResult::Failure => Err(format!("Failed to check host key for {}", host_entry_name(host, port))),
+    }
+}
+
+pub fn list_known_hosts(app: &AppHandle) -> Result<Vec<KnownHostEntry>, String> {
+    let path = known_hosts_path(app)?;
+    let session = Session::new().map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    let known_hosts = load_known_hosts(&session, &path)?;
+
+    let hosts = known_hosts.hosts()
+        .map_err(|e| format!("Failed to list known hosts: {}", e))?;
+    Ok(hosts.iter()
+        .map(|h| {
+            let raw_key = STANDARD.decode(h.key()).unwrap_or_default();
+            KnownHostEntry {
+                host: h.name().unwrap_or("(hashed)").to_string(),
+                key_type: key_type_from_blob(&raw_key),
+                fingerprint: fingerprint(&raw_key),
+            }
+        })
+        .collect())
+}
+
+pub fn remove_known_host(app: &AppHandle, host: &str) -> Result<u32, String> {
+    let path = known_hosts_path(app)?;
+    let session = Session::new().map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    let known_hosts = load_known_hosts(&session, &path)?;
+
+    let mut removed = 0;
+    for entry in known_hosts.hosts().map_err(|e| format!("Failed to list known hosts: {}", e))? {
+        if entry.name() == Some(host) {
+            known_hosts.remove(&entry)
+                .map_err(|e| format!("Failed to remove host key: {}", e))?;
+            removed += 1;
+        }
+    }
+
+    known_hosts.write_file(&path, KnownHostFileKind::OpenSSH)
+        .map_err(|e| format!("Failed to save known hosts file: {}", e))?;
+    Ok(removed)
+}
@@ -0,0 +1,297 @@
+//! A minimal HTTP/1.1 client for the WebDAV and S3 backends, with deliberate limits:
+//!
+//! - Every request opens a new TCP (and TLS) connection and sends `Connection: close`; there is no
+//!   connection reuse, so large directory walks pay a handshake per request.
+//! - Redirects are not followed. A 3xx is returned to the caller like any other status.
+//! - Authentication is whatever headers the caller adds; the WebDAV backend only supports Basic,
+//!   and S3 signs its own requests.
+//! - Responses must be HTTP/1.x with `Content-Length`, chunked encoding or close-delimited bodies.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use crate::net::host_port;
+use crate::proxy::{connect_tcp, ProxySettings};
+
+/// Where requests go: the origin server plus how to reach it.
+#[derive(Clone)]
+pub struct HttpEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    pub proxy: Option<ProxySettings>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+impl HttpEndpoint {
+    fn default_port(&self) -> u16 {
+        if self.tls { 443 } else { 80 }
+    }
+
+    /// `host[:port]` as sent in the `Host` header, omitting the scheme's default port.
+    pub fn authority(&self) -> String {
+        let authority = host_port(&self.host, self.port);
+        if self.port == self.default_port() {
+            authority.rsplit_once(':').map(|(host, _)| host.to_string()).unwrap_or(authority)
+        } else {
+            authority
+        }
+    }
+
+    /// `scheme://authority`, the prefix of absolute URLs on this endpoint.
+    pub fn origin(&self) -> String {
+        format!("{}://{}", if self.tls { "https" } else { "http" }, self.authority())
+    }
+}
+
+pub enum RequestBody<'a> {
+    Empty,
+    Bytes(&'a [u8]),
+    /// A body of known length streamed from a reader.
+    Reader(&'a mut dyn Read, u64),
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The status line plus the start of the body, for error messages.
+    pub fn describe(&self) -> String {
+        let body = String::from_utf8_lossy(&self.body);
+        let body = body.trim();
+        if body.is_empty() || body.starts_with('<') {
+            format!("HTTP {} {}", self.status, self.reason)
+        } else {
+            format!("HTTP {} {}: {}", self.status, self.reason, body.chars().take(200).collect::<String>())
+        }
+    }
+}
+
+enum Connection {
+    Plain(TcpStream),
+    Tls(Box<native_tls::TlsStream<TcpStream>>),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+fn open(endpoint: &HttpEndpoint) -> Result<Connection, String> {
+    let tcp = connect_tcp(endpoint.proxy.as_ref(), &endpoint.host, endpoint.port, endpoint.connect_timeout)?;
+    tcp.set_read_timeout(Some(endpoint.read_timeout))
+        .and_then(|_| tcp.set_write_timeout(Some(endpoint.read_timeout)))
+        .map_err(|e| format!("Failed to set timeout: {}", e))?;
+
+    if !endpoint.tls {
+        return Ok(Connection::Plain(tcp));
+    }
+    let connector = native_tls::TlsConnector::new()
+        .map_err(|e| format!("Failed to create TLS connector: {}", e))?;
+    let tls = connector.connect(&endpoint.host, tcp)
+        .map_err(|e| format!("TLS handshake with {} failed: {}", host_port(&endpoint.host, endpoint.port), e))?;
+    Ok(Connection::Tls(Box::new(tls)))
+}
+
+/// Sends one request on a fresh connection and buffers the whole response body.
+pub fn send(
+    endpoint: &HttpEndpoint,
+    method: &str,
+    target: &str,
+    headers: &[(String, String)],
+    body: RequestBody,
+) -> Result<HttpResponse, String> {
+    let mut body_buf = Vec::new();
+    let mut response = send_to(endpoint, method, target, headers, body, &mut body_buf, true)?;
+    response.body = body_buf;
+    Ok(response)
+}
+
+/// Sends one request on a fresh connection. A successful response body is streamed into `sink`;
+/// error bodies are kept in the returned response unless `stream_errors` is set.
+pub fn send_to(
+    endpoint: &HttpEndpoint,
+    method: &str,
+    target: &str,
+    headers: &[(String, String)],
+    body: RequestBody,
+    sink: &mut dyn Write,
+    stream_errors: bool,
+) -> Result<HttpResponse, String> {
+    let io_err = |e: std::io::Error| format!("HTTP {} {} failed: {}", method, target, e);
+    let mut conn = open(endpoint)?;
+
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: BestFTP\r\nConnection: close\r\n",
+        method, target, endpoint.authority());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    match &body {
+        RequestBody::Empty if method == "GET" || method == "HEAD" || method == "DELETE" => {}
+        RequestBody::Empty => head.push_str("Content-Length: 0\r\n"),
+        RequestBody::Bytes(bytes) => head.push_str(&format!("Content-Length: {}\r\n", bytes.len())),
+        RequestBody::Reader(_, len) => head.push_str(&format!("Content-Length: {}\r\n", len)),
+    }
+    head.push_str("\r\n");
+    conn.write_all(head.as_bytes()).map_err(io_err)?;
+
+    match body {
+        RequestBody::Empty => {}
+        RequestBody::Bytes(bytes) => conn.write_all(bytes).map_err(io_err)?,
+        RequestBody::Reader(reader, len) => {
+            let copied = std::io::copy(&mut reader.take(len), &mut conn).map_err(io_err)?;
+            if copied != len {
+                return Err(format!("HTTP {} {} failed: body ended after {} of {} bytes", method, target, copied, len));
+            }
+        }
+    }
+    conn.flush().map_err(io_err)?;
+
+    let mut reader = BufReader::new(conn);
+    let mut response = loop {
+        let response = read_head(&mut reader).map_err(io_err)?;
+        // Interim 1xx responses (e.g. 100 Continue) are followed by the real one.
+        if !(100..200).contains(&response.status) {
+            break response;
+        }
+    };
+
+    let has_body = method != "HEAD" && response.status != 204 && response.status != 304;
+    if has_body {
+        let chunked = response.header("Transfer-Encoding").is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+        let length = response.header("Content-Length").and_then(|v| v.trim().parse::<u64>().ok());
+        let mut error_body = Vec::new();
+        let out: &mut dyn Write = if response.is_success() || stream_errors { sink } else { &mut error_body };
+        if chunked {
+            copy_chunked(&mut reader, out).map_err(io_err)?;
+        } else if let Some(length) = length {
+            let copied = std::io::copy(&mut (&mut reader).take(length), out).map_err(io_err)?;
+            if copied != length {
+                return Err(format!("HTTP {} {} failed: connection closed after {} of {} bytes", method, target, copied, length));
+            }
+        } else {
+            std::io::copy(&mut reader, out).map_err(io_err)?;
+        }
+        response.body = error_body;
+    }
+    Ok(response)
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn read_head<R: BufRead>(reader: &mut R) -> std::io::Result<HttpResponse> {
+    let status_line = read_line(reader)?;
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or("");
+    let status = parts.next().and_then(|s| s.parse().ok());
+    let (true, Some(status)) = (version.starts_with("HTTP/"), status) else {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid status line '{}'", status_line)));
+    };
+    let reason = parts.next().unwrap_or("").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(HttpResponse { status, reason, headers, body: Vec::new() })
+}
+
+fn copy_chunked<R: BufRead>(reader: &mut R, out: &mut dyn Write) -> std::io::Result<()> {
+    loop {
+        let size_line = read_line(reader)?;
+        let size = u64::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid chunk size"))?;
+        if size == 0 {
+            // Skip trailers up to the terminating blank line.
+            while !read_line(reader)?.is_empty() {}
+            return Ok(());
+        }
+        let copied = std::io::copy(&mut reader.take(size), out)?;
+        if copied != size {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        read_line(reader)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dechunk(input: &[u8]) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        let mut reader = BufReader::new(input);
+        let mut out = Vec::new();
+        copy_chunked(&mut reader, &mut out)?;
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        Ok((out, rest))
+    }
+
+    #[test]
+    fn chunked_body() {
+        let (body, rest) = dechunk(b"5\r\nhello\r\n7;name=value\r\n, world\r\n0\r\n\r\nNEXT").unwrap();
+        assert_eq!(body, b"hello, world");
+        assert_eq!(rest, b"NEXT");
+    }
+
+    #[test]
+    fn chunked_body_with_trailers_and_uppercase_size() {
+        let (body, rest) = dechunk(b"A\r\n0123456789\r\n0\r\nX-Checksum: abc\r\nX-Other: 1\r\n\r\n").unwrap();
+        assert_eq!(body, b"0123456789");
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn chunked_body_errors() {
+        assert_eq!(dechunk(b"zz\r\nhello\r\n0\r\n\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(dechunk(b"10\r\nshort").unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(dechunk(b"5\r\nhello\r\n").unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use crate::models::{ConnectionProtocol, ConnectionState, RemoteSession};
+use crate::commands::common::{get_or_reconnect_stream, get_or_create_sftp_client};
+use crate::reconnect::is_connection_alive;
+
+/// Keeps a session's connection open between commands and reports idle/reconnect/lost transitions.
+/// Once the session has been unused for its idle timeout the connection is closed instead, and the
+/// next command reconnects. Ticks are skipped while a command holds the client, since that traffic
+/// already keeps it alive.
+pub fn spawn_keepalive(session: &Arc<RemoteSession>) {
+    let weak = Arc::downgrade(session);
+
+    tauri::async_runtime::spawn(async move {
+        let options = match weak.upgrade() {
+            Some(session) => session.connection_info.lock().await.as_ref().map(|c| c.options.clone()),
+            None => None,
+        };
+        let Some(options) = options else { return };
+        let keepalive = options.keepalive_interval();
+        let idle_timeout = options.idle_timeout();
+        let interval = match (keepalive, idle_timeout) {
+            (Some(keepalive), Some(idle_timeout)) => keepalive.min(idle_timeout),
+            (Some(interval), None) | (None, Some(interval)) => interval,
+            (None, None) => return,
+        };
+        let mut idle_reported = false;
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let Some(session) = weak.upgrade() else { return };
+            if session.is_closed() {
+                return;
+            }
+
+            let idle = idle_timeout.is_some_and(|timeout| session.idle_for() >= timeout);
+            if idle != idle_reported {
+                let state = if idle { ConnectionState::Idle } else { ConnectionState::Connected };
+                session.emit_state(state, None);
+                idle_reported = idle;
+            }
+            if idle {
+                disconnect_idle(&session).await;
+                continue;
+            }
+            if keepalive.is_none() {
+                continue;
+            }
+
+            let protocol = session.connection_info.lock().await.as_ref().map(|c| c.protocol.clone());
+            match protocol {
+                Some(ConnectionProtocol::SFTP) => {
+                    let client = match session.sftp_client.try_lock() {
+                        Ok(guard) => guard.clone(),
+                        Err(_) => continue,
+                    };
+                    let Some(client) = client else { continue };
+                    let alive = tokio::task::spawn_blocking(move || client.keepalive())
+                        .await
+                        .unwrap_or(false);
+                    if !alive {
+                        let _ = get_or_create_sftp_client(&session).await;
+                    }
+                }
+                // Every WebDAV and S3 request opens its own connection, so there is nothing to keep open.
+                Some(ConnectionProtocol::WebDAV) | Some(ConnectionProtocol::WebDAVS) | Some(ConnectionProtocol::S3) => {}
+                Some(_) => {
+                    let alive = match session.ftp_client.try_lock() {
+                        Ok(mut guard) => match guard.as_mut() {
+                            Some(stream) => session.log.scope(is_connection_alive(stream, options.read_timeout())).await,
+                            None => continue,
+                        },
+                        Err(_) => continue,
+                    };
+                    if !alive {
+                        let _ = session.log.scope(get_or_reconnect_stream(&session)).await;
+                    }
+                }
+                None => return,
+            }
+        }
+    });
+}
+
+/// Closes an idle session's FTP or SFTP connection, leaving its connection info in place so the
+/// next command reconnects. A client held by a command is left alone.
+async fn disconnect_idle(session: &RemoteSession) {
+    let stream = session.ftp_client.try_lock().ok().and_then(|mut guard| guard.take());
+    if let Some(mut stream) = stream {
+        session.log.info("Closing idle connection");
+        let _ = session.log.scope(stream.quit()).await;
+    }
+
+    let client = session.sftp_client.try_lock().ok().and_then(|mut guard| guard.take());
+    if let Some(client) = client {
+        session.log.info("Closing idle connection");
+        let _ = tokio::task::spawn_blocking(move || client.disconnect()).await;
+    }
+}
@@ -2,7 +2,25 @@ mod models;
 mod utils;
 mod commands;
 mod reconnect;
+mod keepalive;
+mod prompts;
+mod host_keys;
+mod sites;
+mod site_import;
+mod vault;
+mod ftp_ops;
+mod ftp_charset;
+mod net;
+mod connection_url;
+mod proxy;
 mod sftp_ops;
+mod http_client;
+mod webdav_ops;
+mod s3_ops;
+mod ssh_tunnel;
+mod ssh_exec;
+mod protocol_probe;
+mod session_log;
 
 use crate::models::FtpState;
 use crate::commands::*;
@@ -16,7 +34,27 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             connect, 
             connect_auto,
+            connect_url,
             disconnect, 
+            list_sessions,
+            respond_auth_prompt,
+            get_session_log,
+            export_session_log,
+            respond_host_key_prompt,
+            list_known_hosts,
+            remove_known_host,
+            list_sites,
+            create_site,
+            update_site,
+            delete_site,
+            list_site_groups,
+            set_site_group,
+            connect_site,
+            import_filezilla_sites,
+            import_winscp_sites,
+            vault_status,
+            unlock_vault,
+            lock_vault,
             list_remote_files,
             list_local_files,
             get_initial_local_path,
@@ -36,6 +74,18 @@ pub fn run() {
             run_executable
         ])
         .setup(|app| {
+            app.state::<FtpState>().prompts.attach(app.handle().clone());
+
+            let vault = app.state::<FtpState>().vault.clone();
+            let vault_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    vault.lock_if_idle(&vault_app);
+                }
+            });
+
             let window = app.get_webview_window("main").unwrap();
             
             window.eval("
@@ -1,9 +1,20 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
 use tokio::sync::Mutex;
-use suppaftp::AsyncFtpStream;
-use ssh2::Session;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
+use crate::ftp_ops::FtpConnection;
+use crate::net::normalize_host;
+use crate::prompts::PendingPrompts;
+use crate::proxy::ProxySettings;
+use crate::sftp_ops::SftpClient;
+use crate::s3_ops::S3Client;
+use crate::session_log::SessionLog;
+use crate::webdav_ops::WebDavClient;
+use crate::vault::Vault;
 
 #[derive(Clone)]
 pub struct ConnectionInfo {
@@ -12,21 +23,277 @@ pub struct ConnectionInfo {
     pub username: String,
     pub password: String,
     pub protocol: ConnectionProtocol,
+    pub options: ConnectionOptions,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionOptions {
+    pub private_key_path: Option<String>,
+    pub key_passphrase: Option<String>,
+    pub use_agent: bool,
+    pub agent_only: bool,
+    pub proxy: Option<ProxySettings>,
+    pub jump_hosts: Vec<JumpHost>,
+    pub data_mode: FtpDataMode,
+    pub active_port_range: Option<PortRange>,
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    /// Seconds without a command before the connection is closed; `0` keeps it open.
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds between keepalives on an idle session; `0` disables them.
+    pub keepalive_interval_secs: Option<u64>,
+    /// Region used to sign S3 requests; defaults to `us-east-1`.
+    pub s3_region: Option<String>,
+    /// Whether to reach the S3 endpoint over HTTPS; defaults to true on port 443 only.
+    pub s3_use_tls: Option<bool>,
+    /// Filename encoding for FTP servers without UTF-8 support, as a label such as `windows-1251`;
+    /// unset or `auto` uses UTF-8 and turns it on when the server advertises it.
+    pub ftp_charset: Option<String>,
+}
+
+impl ConnectionOptions {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs.unwrap_or(10).max(1))
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs.unwrap_or(30).max(1))
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout_secs.unwrap_or(300) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        match self.keepalive_interval_secs.unwrap_or(60) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FtpDataMode {
+    #[default]
+    Passive,
+    ExtendedPassive,
+    Active,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JumpHost {
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub key_passphrase: Option<String>,
+    #[serde(default)]
+    pub use_agent: bool,
+    #[serde(default)]
+    pub agent_only: bool,
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+impl JumpHost {
+    pub fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            host: normalize_host(&self.host),
+            port: self.port,
+            username: self.username.clone(),
+            password: self.password.clone().unwrap_or_default(),
+            protocol: ConnectionProtocol::SFTP,
+            options: ConnectionOptions {
+                private_key_path: self.private_key_path.clone(),
+                key_passphrase: self.key_passphrase.clone(),
+                use_agent: self.use_agent,
+                agent_only: self.agent_only,
+                ..ConnectionOptions::default()
+            },
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ConnectionProtocol {
     FTP,
+    FTPS,
+    FTPSImplicit,
     SFTP,
+    WebDAV,
+    WebDAVS,
+    S3,
 }
 
-#[derive(Default)]
-pub struct FtpState {
-    pub ftp_client: Arc<Mutex<Option<AsyncFtpStream>>>,
-    pub sftp_session: Arc<Mutex<Option<Session>>>,
-    pub sftp_tcp: Arc<Mutex<Option<std::net::TcpStream>>>,
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionState {
+    Connected,
+    Idle,
+    Reconnecting,
+    Lost,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ConnectionStateEvent {
+    pub session_id: String,
+    pub state: ConnectionState,
+    pub message: Option<String>,
+}
+
+pub struct RemoteSession {
+    pub id: String,
+    pub ftp_client: Arc<Mutex<Option<FtpConnection>>>,
+    pub sftp_client: Arc<Mutex<Option<SftpClient>>>,
+    pub webdav_client: Arc<Mutex<Option<WebDavClient>>>,
+    pub s3_client: Arc<Mutex<Option<S3Client>>>,
     pub current_path: Arc<Mutex<String>>,
     pub connection_info: Arc<Mutex<Option<ConnectionInfo>>>,
+    pub prompts: Arc<PendingPrompts>,
+    pub log: Arc<SessionLog>,
+    pub last_activity: std::sync::Mutex<Instant>,
+    pub closed: AtomicBool,
+}
+
+impl RemoteSession {
+    pub fn new(conn_info: ConnectionInfo, prompts: Arc<PendingPrompts>) -> Self {
+        let id = uuid::Uuid::new_v4().to_string();
+        RemoteSession {
+            log: SessionLog::new(id.clone(), prompts.clone()),
+            id,
+            ftp_client: Arc::new(Mutex::new(None)),
+            sftp_client: Arc::new(Mutex::new(None)),
+            webdav_client: Arc::new(Mutex::new(None)),
+            s3_client: Arc::new(Mutex::new(None)),
+            current_path: Arc::new(Mutex::new("/".to_string())),
+            connection_info: Arc::new(Mutex::new(Some(conn_info))),
+            prompts,
+            last_activity: std::sync::Mutex::new(Instant::now()),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity.lock().unwrap().elapsed()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn emit_state(&self, state: ConnectionState, message: Option<String>) {
+        if let Some(app) = self.prompts.app() {
+            let _ = app.emit("connection-state", ConnectionStateEvent {
+                session_id: self.id.clone(),
+                state,
+                message,
+            });
+        }
+    }
+}
+
+/// Transcripts of failed connection attempts kept for export; older ones are dropped.
+const FAILED_LOG_CAPACITY: usize = 20;
+
+#[derive(Default)]
+pub struct FtpState {
+    pub sessions: Arc<Mutex<HashMap<String, Arc<RemoteSession>>>>,
+    pub prompts: Arc<PendingPrompts>,
+    pub vault: Arc<Vault>,
+    /// Transcripts of connection attempts that never became a session.
+    pub failed_logs: Arc<Mutex<VecDeque<Arc<SessionLog>>>>,
+}
+
+impl FtpState {
+    pub async fn session(&self, session_id: &str) -> Result<Arc<RemoteSession>, String> {
+        let session = self.sessions.lock().await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| format!("No active session with id '{}'", session_id))?;
+        session.touch();
+        Ok(session)
+    }
+
+    /// The session behind a command that works on either side; only remote calls need an id.
+    pub async fn remote_session(&self, session_id: Option<&str>) -> Result<Arc<RemoteSession>, String> {
+        let session_id = session_id.ok_or("A remote operation needs a session id")?;
+        self.session(session_id).await
+    }
+
+    /// The transcript of a session or failed connection attempt. Reading it is not activity, so
+    /// unlike `session` this leaves the idle timer alone.
+    pub async fn session_log(&self, session_id: &str) -> Result<Arc<SessionLog>, String> {
+        if let Some(session) = self.sessions.lock().await.get(session_id) {
+            return Ok(session.log.clone());
+        }
+        self.failed_logs.lock().await.iter()
+            .find(|log| log.session_id() == session_id)
+            .cloned()
+            .ok_or_else(|| format!("No active session with id '{}'", session_id))
+    }
+
+    pub async fn keep_failed_log(&self, log: Arc<SessionLog>) {
+        let mut logs = self.failed_logs.lock().await;
+        if logs.len() == FAILED_LOG_CAPACITY {
+            logs.pop_front();
+        }
+        logs.push_back(log);
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ConnectedSession {
+    pub session_id: String,
+    pub message: String,
+    /// What auto-detection settled on, for connections made without an explicit protocol.
+    pub detected: Option<ProtocolDetection>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ProtocolDetection {
+    pub protocol: ConnectionProtocol,
+    /// The banner or handshake that decided it.
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SiteConnection {
+    #[serde(flatten)]
+    pub session: ConnectedSession,
+    pub remote_dir: Option<String>,
+    pub local_dir: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub protocol: ConnectionProtocol,
+    pub current_path: String,
 }
 
 #[derive(Serialize, Clone)]
@@ -53,3 +320,15 @@ pub struct CommandResult<T> {
     pub data: Option<T>,
     pub error: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn remote_commands_need_a_session_id() {
+        let state = FtpState::default();
+        assert_eq!(state.remote_session(None).await.err().unwrap(), "A remote operation needs a session id");
+        assert!(state.remote_session(Some("gone")).await.err().unwrap().contains("'gone'"));
+    }
+}
@@ -0,0 +1,84 @@
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Strips the brackets from an IPv6 literal such as `[::1]`; other hosts are returned trimmed.
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+        .to_string()
+}
+
+/// Formats `host:port`, bracketing IPv6 literals.
+pub fn host_port(host: &str, port: u16) -> String {
+    let host = normalize_host(host);
+    if host.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()) {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let host = normalize_host(host);
+    let addrs: Vec<SocketAddr> = (host.as_str(), port).to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("{} did not resolve to any address", host));
+    }
+
+    // Alternate address families so a broken IPv6 route does not delay IPv4 (RFC 8305).
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|a| a.is_ipv6());
+    let mut ordered = Vec::with_capacity(v6.len() + v4.len());
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    Ok(ordered)
+}
+
+/// Connects to the first address of `host` that answers. Attempts are started in turn,
+/// each one `ATTEMPT_DELAY` after the previous, and the first successful one wins.
+pub fn connect_host(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = resolve(host, port)?;
+    if addrs.len() == 1 {
+        return TcpStream::connect_timeout(&addrs[0], timeout)
+            .map_err(|e| format!("Failed to connect to {}: {}", host_port(host, port), e));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let connected = Arc::new(AtomicBool::new(false));
+    for (i, addr) in addrs.iter().copied().enumerate() {
+        let tx = tx.clone();
+        let connected = connected.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(ATTEMPT_DELAY * i as u32);
+            if connected.load(Ordering::SeqCst) {
+                return;
+            }
+            let _ = tx.send((addr, TcpStream::connect_timeout(&addr, timeout)));
+        });
+    }
+    drop(tx);
+
+    let mut errors = Vec::new();
+    for (addr, result) in rx {
+        match result {
+            Ok(stream) => {
+                connected.store(true, Ordering::SeqCst);
+                return Ok(stream);
+            }
+            Err(e) => errors.push(format!("{}: {}", addr, e)),
+        }
+    }
+    Err(format!("Failed to connect to {}: {}", host_port(host, port), errors.join("; ")))
+}
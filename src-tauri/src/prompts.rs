@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::Duration;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub struct PendingPrompts {
+    app: OnceLock<AppHandle>,
+    senders: Mutex<HashMap<String, mpsc::Sender<Option<Vec<String>>>>>,
+}
+
+impl PendingPrompts {
+    pub fn attach(&self, app: AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    pub fn app(&self) -> Option<&AppHandle> {
+        self.app.get()
+    }
+
+    pub fn ask<T: Serialize + Clone>(&self, event: &str, request_id: &str, payload: T) -> Option<Vec<String>> {
+        let app = self.app.get()?;
+        let (tx, rx) = mpsc::channel();
+        self.senders.lock().unwrap().insert(request_id.to_string(), tx);
+
+        if app.emit(event, payload).is_err() {
+            self.senders.lock().unwrap().remove(request_id);
+            return None;
+        }
+
+        let answer = rx.recv_timeout(PROMPT_TIMEOUT).ok().flatten();
+        self.senders.lock().unwrap().remove(request_id);
+        answer
+    }
+
+    pub fn answer(&self, request_id: &str, responses: Option<Vec<String>>) -> bool {
+        match self.senders.lock().unwrap().remove(request_id) {
+            Some(tx) => tx.send(responses).is_ok(),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use crate::models::{ConnectionInfo, ConnectionProtocol, ProtocolDetection};
+use crate::net::host_port;
+use crate::proxy::connect_tcp;
+
+/// How long a server gets to greet us before we assume it is waiting for a TLS handshake.
+const BANNER_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BANNER_LEN: usize = 512;
+
+/// Works out which protocol the server speaks from what it sends before any login. SSH servers
+/// announce `SSH-2.0-...` and FTP servers a `220` reply, after which `AUTH TLS` tells FTPS from
+/// plain FTP; a server that stays silent is offered a TLS handshake, after which an implicit FTPS
+/// server greets like a plain one.
+pub fn detect_protocol(conn_info: &ConnectionInfo) -> Result<ProtocolDetection, String> {
+    let address = host_port(&conn_info.host, conn_info.port);
+    let stream = open(conn_info)?;
+    let mut reader = BufReader::new(&stream);
+    if let Some(banner) = read_banner(&mut reader)? {
+        return match classify(&banner) {
+            Some(ConnectionProtocol::SFTP) => Ok(detected(ConnectionProtocol::SFTP, format!("SSH banner: {}", banner))),
+            Some(_) => probe_auth_tls(&mut reader, &stream, &banner),
+            None if is_ftp_reply(&banner) => Err(format!("FTP server at {} is not accepting connections: {}", address, banner)),
+            None => Err(format!("Unrecognized banner from {}: {}", address, banner)),
+        };
+    }
+
+    // The handshake only tells us whether TLS is spoken; certificates are checked when we log in.
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()
+        .map_err(|e| format!("Failed to create TLS connector: {}", e))?;
+    let tls = connector.connect(&conn_info.host, open(conn_info)?)
+        .map_err(|e| format!("{} sent no banner within {}s and did not accept TLS: {}", address, BANNER_TIMEOUT.as_secs(), e))?;
+    match read_banner(&mut BufReader::new(tls))? {
+        Some(banner) if matches!(classify(&banner), Some(ConnectionProtocol::FTP)) => Ok(detected(
+            ConnectionProtocol::FTPSImplicit,
+            format!("No plain-text banner; FTP greeting after TLS handshake: {}", banner),
+        )),
+        Some(banner) => Err(format!("Unrecognized banner from {} after TLS handshake: {}", address, banner)),
+        None => Err(format!("{} accepted TLS but sent no banner; choose the protocol explicitly", address)),
+    }
+}
+
+fn detected(protocol: ConnectionProtocol, reason: String) -> ProtocolDetection {
+    ProtocolDetection { protocol, reason }
+}
+
+fn open(conn_info: &ConnectionInfo) -> Result<TcpStream, String> {
+    let options = &conn_info.options;
+    let stream = connect_tcp(options.proxy.as_ref(), &conn_info.host, conn_info.port, options.connect_timeout())?;
+    stream.set_read_timeout(Some(BANNER_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(BANNER_TIMEOUT)))
+        .map_err(|e| format!("Failed to set timeout: {}", e))?;
+    Ok(stream)
+}
+
+/// Reads the first line the server sends, or `None` if it sends nothing before the timeout.
+fn read_banner<R: BufRead>(reader: &mut R) -> Result<Option<String>, String> {
+    match read_line(reader) {
+        Ok(line) if line.is_empty() => Err("Server closed the connection without sending a banner".to_string()),
+        Ok(line) => Ok(Some(line)),
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+        Err(e) => Err(format!("Failed to read server banner: {}", e)),
+    }
+}
+
+/// Reads up to `MAX_BANNER_LEN` bytes of one line, without the line ending. Empty at end of stream.
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    reader.take(MAX_BANNER_LEN as u64).read_until(b'\n', &mut line)?;
+    Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+}
+
+/// Reads lines up to the last one of the reply that `first` starts, which is returned.
+fn finish_reply<R: BufRead>(reader: &mut R, first: String) -> std::io::Result<String> {
+    let code = first.get(..3).unwrap_or_default().to_string();
+    let mut line = first;
+    while !(line.starts_with(&code) && line.as_bytes().get(3) == Some(&b' ')) {
+        line = read_line(reader)?;
+        if line.is_empty() {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+    }
+    Ok(line)
+}
+
+/// Asks an FTP server for explicit TLS. A server that accepts is reported as FTPS so that the
+/// login never goes out in plain text; only a refusal makes it plain FTP.
+fn probe_auth_tls<R: BufRead, W: Write>(reader: &mut R, mut writer: W, greeting: &str) -> Result<ProtocolDetection, String> {
+    let io_err = |e: std::io::Error| format!("FTP greeting received, but AUTH TLS failed: {}", e);
+    finish_reply(reader, greeting.to_string()).map_err(io_err)?;
+    writer.write_all(b"AUTH TLS\r\n").and_then(|_| writer.flush()).map_err(io_err)?;
+    let first = read_line(reader).map_err(io_err)?;
+    let reply = finish_reply(reader, first).map_err(io_err)?;
+
+    if reply.starts_with("234") {
+        Ok(detected(ConnectionProtocol::FTPS, format!("FTP greeting: {}; AUTH TLS accepted: {}", greeting, reply)))
+    } else if is_ftp_reply(&reply) && reply.starts_with('5') {
+        Ok(detected(ConnectionProtocol::FTP, format!("FTP greeting: {}; AUTH TLS refused: {}", greeting, reply)))
+    } else {
+        Err(format!("FTP greeting received, but the answer to AUTH TLS was unexpected: {}", reply))
+    }
+}
+
+/// SSH means SFTP, and a `220` reply (possibly the first line of a multi-line one) means FTP.
+fn classify(banner: &str) -> Option<ConnectionProtocol> {
+    if banner.starts_with("SSH-") {
+        Some(ConnectionProtocol::SFTP)
+    } else if is_ftp_reply(banner) && banner.starts_with("220") {
+        Some(ConnectionProtocol::FTP)
+    } else {
+        None
+    }
+}
+
+fn is_ftp_reply(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    bytes.len() >= 3 && bytes[..3].iter().all(u8::is_ascii_digit) && matches!(bytes.get(3), None | Some(b' ' | b'-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banners() {
+        assert!(matches!(classify("220 ProFTPD Server ready."), Some(ConnectionProtocol::FTP)));
+        assert!(matches!(classify("220-Welcome to Pure-FTPd"), Some(ConnectionProtocol::FTP)));
+        assert!(matches!(classify("SSH-2.0-OpenSSH_9.6"), Some(ConnectionProtocol::SFTP)));
+        assert!(matches!(classify("SSH-1.99-OpenSSH_3.9p1"), Some(ConnectionProtocol::SFTP)));
+        assert!(classify("421 Too many connections").is_none());
+        assert!(classify("HTTP/1.1 400 Bad Request").is_none());
+        assert!(classify("2200 not a reply").is_none());
+    }
+
+    #[test]
+    fn ftp_replies() {
+        assert!(is_ftp_reply("220 Ready"));
+        assert!(is_ftp_reply("220-Multi-line"));
+        assert!(is_ftp_reply("421 Service not available"));
+        assert!(is_ftp_reply("220"));
+        assert!(!is_ftp_reply("22"));
+        assert!(!is_ftp_reply("2200"));
+        assert!(!is_ftp_reply("SSH-1.99-OpenSSH"));
+        assert!(!is_ftp_reply("abc def"));
+    }
+
+    fn probe(after_banner: &str) -> (Result<ProtocolDetection, String>, Vec<u8>) {
+        let mut reader = BufReader::new(after_banner.as_bytes());
+        let mut sent = Vec::new();
+        (probe_auth_tls(&mut reader, &mut sent, "220-Welcome"), sent)
+    }
+
+    #[test]
+    fn auth_tls_decides_between_ftp_and_ftps() {
+        let (result, sent) = probe("220-Second line\r\n220 Ready\r\n234 Proceed with negotiation.\r\n");
+        assert_eq!(sent, b"AUTH TLS\r\n");
+        assert!(matches!(result.unwrap().protocol, ConnectionProtocol::FTPS));
+
+        let (result, _) = probe("220 Ready\r\n530-TLS is not\r\n530 configured\r\n");
+        let detection = result.unwrap();
+        assert!(matches!(detection.protocol, ConnectionProtocol::FTP));
+        assert!(detection.reason.contains("530 configured"), "{}", detection.reason);
+
+        assert!(probe("220 Ready\r\n421 Try again later\r\n").0.is_err());
+        assert!(probe("220 Ready\r\n").0.is_err());
+    }
+}
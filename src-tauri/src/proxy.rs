@@ -0,0 +1,402 @@
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Serialize, Deserialize};
+use crate::net::{connect_host, host_port, normalize_host};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyKind {
+    Socks5,
+    Http,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProxySettings {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Opens a TCP connection to `host:port`, tunnelled through `proxy` when one is configured.
+pub fn connect_tcp(proxy: Option<&ProxySettings>, host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let Some(proxy) = proxy else {
+        return connect_host(host, port, timeout);
+    };
+
+    let mut stream = connect_host(&proxy.host, proxy.port, timeout)
+        .map_err(|e| format!("Proxy: {}", e))?;
+
+    let set_timeout = |stream: &TcpStream, timeout: Option<Duration>| {
+        stream.set_read_timeout(timeout)
+            .and_then(|_| stream.set_write_timeout(timeout))
+            .map_err(|e| format!("Proxy: failed to set timeout: {}", e))
+    };
+    set_timeout(&stream, Some(timeout))?;
+    match proxy.kind {
+        ProxyKind::Socks5 => socks5_handshake(&mut stream, proxy, host, port)?,
+        ProxyKind::Http => http_connect_handshake(&mut stream, proxy, host, port)?,
+    }
+    set_timeout(&stream, None)?;
+    Ok(stream)
+}
+
+/// Async counterpart of [`connect_tcp`] producing the stream type suppaftp expects.
+pub async fn connect_tcp_async(proxy: Option<ProxySettings>, host: String, port: u16, timeout: Duration) -> Result<async_std::net::TcpStream, String> {
+    async_std::task::spawn_blocking(move || connect_tcp(proxy.as_ref(), &host, port, timeout))
+        .await
+        .map(async_std::net::TcpStream::from)
+}
+
+/// Exposes an already-connected stream on a loopback port, for APIs that only accept an address to dial.
+/// Accepts a single loopback client within `timeout` and pumps bytes in both directions until either
+/// side closes.
+pub fn forward_once(remote: TcpStream, timeout: Duration) -> Result<SocketAddr, String> {
+    let open_failed = |e: std::io::Error| format!("Failed to open local forwarding port: {}", e);
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(open_failed)?;
+    let local_addr = listener.local_addr().map_err(open_failed)?;
+    listener.set_nonblocking(true).map_err(open_failed)?;
+
+    std::thread::spawn(move || {
+        let Some(local) = accept_local(&listener, local_addr, timeout) else { return };
+        // Dropping the listener stops anyone else from dialing in.
+        drop(listener);
+        if local.set_nonblocking(false).is_err() {
+            return;
+        }
+        let (Ok(mut local_read), Ok(mut remote_write)) = (local.try_clone(), remote.try_clone()) else { return };
+        let mut remote_read = remote;
+        let mut local_write = local;
+
+        let upstream = std::thread::spawn(move || {
+            let _ = std::io::copy(&mut local_read, &mut remote_write);
+            let _ = remote_write.shutdown(std::net::Shutdown::Write);
+        });
+        let _ = std::io::copy(&mut remote_read, &mut local_write);
+        let _ = local_write.shutdown(std::net::Shutdown::Write);
+        let _ = upstream.join();
+    });
+
+    Ok(local_addr)
+}
+
+/// Waits up to `timeout` on a non-blocking listener for a client on this machine, turning away
+/// anything else, including the listener connecting to itself.
+fn accept_local(listener: &TcpListener, own_addr: SocketAddr, timeout: Duration) -> Option<TcpStream> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, peer)) if peer.ip().is_loopback() && peer != own_addr => return Some(stream),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(10)),
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+fn socks5_handshake(stream: &mut TcpStream, proxy: &ProxySettings, host: &str, port: u16) -> Result<(), String> {
+    let io_err = |e: std::io::Error| format!("SOCKS5 proxy error: {}", e);
+    let credentials = proxy.username.as_ref().map(|u| (u.as_str(), proxy.password.as_deref().unwrap_or("")));
+
+    let greeting: &[u8] = if credentials.is_some() { &[5, 2, 0x00, 0x02] } else { &[5, 1, 0x00] };
+    stream.write_all(greeting).map_err(io_err)?;
+
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).map_err(io_err)?;
+    if choice[0] != 5 {
+        return Err("SOCKS5 proxy error: not a SOCKS5 server".to_string());
+    }
+
+    match (choice[1], credentials) {
+        (0x00, _) => {}
+        (0x02, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err("SOCKS5 proxy error: username and password must be at most 255 bytes".to_string());
+            }
+            let mut auth = vec![1, username.len() as u8];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).map_err(io_err)?;
+
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).map_err(io_err)?;
+            if status[1] != 0 {
+                return Err("SOCKS5 proxy rejected the username or password".to_string());
+            }
+        }
+        _ => return Err("SOCKS5 proxy does not accept any offered authentication method".to_string()),
+    }
+
+    let host = normalize_host(host);
+    let mut request = vec![5, 1, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            if host.len() > 255 {
+                return Err("SOCKS5 proxy error: host name is too long".to_string());
+            }
+            request.push(3);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).map_err(io_err)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).map_err(io_err)?;
+    if reply[1] != 0 {
+        let reason = match reply[1] {
+            1 => "general failure",
+            2 => "connection not allowed by ruleset",
+            3 => "network unreachable",
+            4 => "host unreachable",
+            5 => "connection refused",
+            6 => "TTL expired",
+            7 => "command not supported",
+            8 => "address type not supported",
+            _ => "unknown error",
+        };
+        return Err(format!("SOCKS5 proxy could not reach {}: {}", host_port(&host, port), reason));
+    }
+
+    let bound_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).map_err(io_err)?;
+            len[0] as usize
+        }
+        _ => return Err("SOCKS5 proxy error: invalid reply".to_string()),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).map_err(io_err)?;
+    Ok(())
+}
+
+fn http_connect_handshake(stream: &mut TcpStream, proxy: &ProxySettings, host: &str, port: u16) -> Result<(), String> {
+    let io_err = |e: std::io::Error| format!("HTTP proxy error: {}", e);
+    let target = host_port(host, port);
+
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if let Some(username) = &proxy.username {
+        let credentials = format!("{}:{}", username, proxy.password.as_deref().unwrap_or(""));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", STANDARD.encode(credentials)));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).map_err(io_err)?;
+
+    // Read byte by byte so nothing past the header block is consumed from the tunnel.
+    let mut response = Vec::new();
+    let mut byte = [0u8; 1];
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() > 16 * 1024 {
+            return Err("HTTP proxy error: response header too large".to_string());
+        }
+        stream.read_exact(&mut byte).map_err(io_err)?;
+        response.push(byte[0]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or("");
+    let status = status_line.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {
+        return Err(format!("HTTP proxy refused CONNECT to {}: {}", target, status_line));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Runs `serve` on the first connection to a loopback listener standing in for the proxy.
+    fn fake_proxy(serve: impl FnOnce(TcpStream) + Send + 'static) -> (u16, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream);
+        });
+        (port, handle)
+    }
+
+    fn proxy(kind: ProxyKind, port: u16, credentials: Option<(&str, &str)>) -> ProxySettings {
+        ProxySettings {
+            kind,
+            host: "127.0.0.1".to_string(),
+            port,
+            username: credentials.map(|(user, _)| user.to_string()),
+            password: credentials.map(|(_, pass)| pass.to_string()),
+        }
+    }
+
+    fn read_vec(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    /// Reads a SOCKS5 CONNECT request for a domain name, checks the target and grants it.
+    fn accept_socks5_connect(stream: &mut TcpStream, host: &str, port: u16) {
+        assert_eq!(read_vec(stream, 4), [5, 1, 0, 3]);
+        let len = read_vec(stream, 1)[0] as usize;
+        assert_eq!(read_vec(stream, len), host.as_bytes());
+        assert_eq!(read_vec(stream, 2), port.to_be_bytes());
+        stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 21]).unwrap();
+        stream.write_all(b"220 tunnel").unwrap();
+    }
+
+    fn read_tunnel(mut stream: TcpStream) -> String {
+        let mut greeting = [0u8; 10];
+        stream.read_exact(&mut greeting).unwrap();
+        String::from_utf8_lossy(&greeting).to_string()
+    }
+
+    #[test]
+    fn socks5_without_auth() {
+        let (port, server) = fake_proxy(|mut stream| {
+            assert_eq!(read_vec(&mut stream, 3), [5, 1, 0]);
+            stream.write_all(&[5, 0]).unwrap();
+            accept_socks5_connect(&mut stream, "files.example.com", 21);
+        });
+
+        let stream = connect_tcp(Some(&proxy(ProxyKind::Socks5, port, None)), "files.example.com", 21, TIMEOUT).unwrap();
+        assert_eq!(read_tunnel(stream), "220 tunnel");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn socks5_with_username_and_password() {
+        let (port, server) = fake_proxy(|mut stream| {
+            assert_eq!(read_vec(&mut stream, 4), [5, 2, 0, 2]);
+            stream.write_all(&[5, 2]).unwrap();
+            assert_eq!(read_vec(&mut stream, 2), [1, 4]);
+            assert_eq!(read_vec(&mut stream, 4), b"user");
+            assert_eq!(read_vec(&mut stream, 1), [6]);
+            assert_eq!(read_vec(&mut stream, 6), b"s3cret");
+            stream.write_all(&[1, 0]).unwrap();
+            accept_socks5_connect(&mut stream, "files.example.com", 2121);
+        });
+
+        let settings = proxy(ProxyKind::Socks5, port, Some(("user", "s3cret")));
+        let stream = connect_tcp(Some(&settings), "files.example.com", 2121, TIMEOUT).unwrap();
+        assert_eq!(read_tunnel(stream), "220 tunnel");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn socks5_rejected_password() {
+        let (port, server) = fake_proxy(|mut stream| {
+            read_vec(&mut stream, 4);
+            stream.write_all(&[5, 2]).unwrap();
+            read_vec(&mut stream, 2 + 4 + 1 + 5);
+            stream.write_all(&[1, 1]).unwrap();
+        });
+
+        let settings = proxy(ProxyKind::Socks5, port, Some(("user", "wrong")));
+        let error = connect_tcp(Some(&settings), "files.example.com", 21, TIMEOUT).unwrap_err();
+        assert!(error.contains("rejected the username or password"), "{}", error);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn socks5_ipv6_target() {
+        let (port, server) = fake_proxy(|mut stream| {
+            read_vec(&mut stream, 3);
+            stream.write_all(&[5, 0]).unwrap();
+            assert_eq!(read_vec(&mut stream, 4), [5, 1, 0, 4]);
+            assert_eq!(read_vec(&mut stream, 16), "2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+            assert_eq!(read_vec(&mut stream, 2), 22u16.to_be_bytes());
+            stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+            stream.write_all(b"SSH-2.0-x\n").unwrap();
+        });
+
+        let stream = connect_tcp(Some(&proxy(ProxyKind::Socks5, port, None)), "[2001:db8::1]", 22, TIMEOUT).unwrap();
+        assert_eq!(read_tunnel(stream), "SSH-2.0-x\n");
+        server.join().unwrap();
+    }
+
+    /// Reads the CONNECT request header block from the client.
+    fn read_http_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.extend(read_vec(stream, 1));
+        }
+        String::from_utf8(request).unwrap()
+    }
+
+    #[test]
+    fn http_connect_with_basic_auth() {
+        let (port, server) = fake_proxy(|mut stream| {
+            let request = read_http_request(&mut stream);
+            assert!(request.starts_with("CONNECT files.example.com:21 HTTP/1.1\r\n"), "{}", request);
+            assert!(request.contains("Proxy-Authorization: Basic dXNlcjpwYXNz\r\n"), "{}", request);
+            // The tunnelled greeting arrives in the same write; none of it may be eaten as header.
+            stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n220 tunnel").unwrap();
+        });
+
+        let settings = proxy(ProxyKind::Http, port, Some(("user", "pass")));
+        let stream = connect_tcp(Some(&settings), "files.example.com", 21, TIMEOUT).unwrap();
+        assert_eq!(read_tunnel(stream), "220 tunnel");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn http_connect_refused() {
+        let (port, server) = fake_proxy(|mut stream| {
+            let request = read_http_request(&mut stream);
+            assert!(!request.contains("Proxy-Authorization"), "{}", request);
+            stream.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n").unwrap();
+        });
+
+        let error = connect_tcp(Some(&proxy(ProxyKind::Http, port, None)), "files.example.com", 21, TIMEOUT).unwrap_err();
+        assert!(error.contains("407"), "{}", error);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn forwarding_relays_one_client() {
+        let (port, server) = fake_proxy(|mut stream| {
+            stream.write_all(b"220 forwarded").unwrap();
+            assert_eq!(read_vec(&mut stream, 4), b"QUIT");
+        });
+        let remote = TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        let local_addr = forward_once(remote, TIMEOUT).unwrap();
+        let mut client = TcpStream::connect(local_addr).unwrap();
+        assert_eq!(read_vec(&mut client, 13), b"220 forwarded");
+        client.write_all(b"QUIT").unwrap();
+        server.join().unwrap();
+        // The port only ever serves the first client.
+        assert!(TcpStream::connect(local_addr).is_err());
+    }
+
+    #[test]
+    fn forwarding_gives_up_without_a_client() {
+        let (port, server) = fake_proxy(|_| {});
+        let remote = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let local_addr = forward_once(remote, Duration::from_millis(50)).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(TcpStream::connect(local_addr).is_err());
+        server.join().unwrap();
+    }
+}
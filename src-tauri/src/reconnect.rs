@@ -1,10 +1,11 @@
-use suppaftp::AsyncFtpStream;
+use crate::ftp_ops::FtpConnection;
 use crate::models::ConnectionInfo;
+use crate::ftp_ops::connect_ftp;
 
 pub async fn reconnect_with_retry(
     conn_info: &ConnectionInfo,
     max_attempts: u32,
-) -> Result<AsyncFtpStream, String> {
+) -> Result<FtpConnection, String> {
     let mut attempt = 0;
     let mut last_error = String::new();
 
@@ -16,22 +17,10 @@ pub async fn reconnect_with_retry(
             tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms.min(10000))).await;
         }
 
-        let addr = format!("{}:{}", conn_info.host, conn_info.port);
-        
-        match AsyncFtpStream::connect(&addr).await {
-            Ok(mut stream) => {
-                match stream.login(&conn_info.username, &conn_info.password).await {
-                    Ok(_) => {
-                        let _ = stream.transfer_type(suppaftp::types::FileType::Binary).await;
-                        return Ok(stream);
-                    }
-                    Err(e) => {
-                        last_error = format!("Login failed on attempt {}: {}", attempt, e);
-                    }
-                }
-            }
+        match connect_ftp(conn_info).await {
+            Ok(stream) => return Ok(stream),
             Err(e) => {
-                last_error = format!("Connection failed on attempt {}: {}", attempt, e);
+                last_error = format!("{} (attempt {})", e, attempt);
             }
         }
     }
@@ -39,6 +28,6 @@ pub async fn reconnect_with_retry(
     Err(format!("Failed to reconnect after {} attempts. Last error: {}", max_attempts, last_error))
 }
 
-pub async fn is_connection_alive(stream: &mut AsyncFtpStream) -> bool {
-    stream.noop().await.is_ok()
+pub async fn is_connection_alive(stream: &mut FtpConnection, timeout: std::time::Duration) -> bool {
+    matches!(tokio::time::timeout(timeout, stream.noop()).await, Ok(Ok(())))
 }
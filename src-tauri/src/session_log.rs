@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::Emitter;
+use crate::prompts::PendingPrompts;
+
+/// Lines kept per session; older ones are dropped once the buffer is full.
+const LOG_CAPACITY: usize = 2000;
+const MASKED_COMMANDS: [&str; 2] = ["PASS", "ACCT"];
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogDirection {
+    Sent,
+    Received,
+    Info,
+    Error,
+}
+
+impl LogDirection {
+    fn marker(self) -> &'static str {
+        match self {
+            LogDirection::Sent => ">",
+            LogDirection::Received => "<",
+            LogDirection::Info => "*",
+            LogDirection::Error => "!",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct SessionLogEntry {
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub direction: LogDirection,
+    pub message: String,
+}
+
+/// A session's protocol transcript. Every line is streamed to the frontend as a `session-log`
+/// event and kept in a rolling buffer that can be exported.
+pub struct SessionLog {
+    session_id: String,
+    prompts: Arc<PendingPrompts>,
+    entries: Mutex<VecDeque<SessionLogEntry>>,
+}
+
+tokio::task_local! {
+    static CURRENT: Arc<SessionLog>;
+}
+
+impl SessionLog {
+    pub fn new(session_id: String, prompts: Arc<PendingPrompts>) -> Arc<Self> {
+        Arc::new(SessionLog {
+            session_id,
+            prompts,
+            entries: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Records a command sent to the server, with any password masked.
+    pub fn sent(&self, line: &str) {
+        self.push(LogDirection::Sent, mask_secret(line));
+    }
+
+    pub fn received(&self, line: &str) {
+        self.push(LogDirection::Received, line.to_string());
+    }
+
+    pub fn info(&self, message: impl Into<String>) {
+        self.push(LogDirection::Info, message.into());
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.push(LogDirection::Error, message.into());
+    }
+
+    fn push(&self, direction: LogDirection, message: String) {
+        let entry = SessionLogEntry {
+            session_id: self.session_id.clone(),
+            timestamp: Utc::now(),
+            direction,
+            message,
+        };
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() == LOG_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+        if let Some(app) = self.prompts.app() {
+            let _ = app.emit("session-log", entry);
+        }
+    }
+
+    pub fn entries(&self) -> Vec<SessionLogEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Writes the buffered transcript to `path` as plain text, one line per entry.
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let mut file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        for entry in self.entries() {
+            writeln!(file, "{} {} {}", entry.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"), entry.direction.marker(), entry.message)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Runs `future` with this transcript as the one FTP traffic from the current task goes to.
+    pub async fn scope<F: Future>(self: &Arc<Self>, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+}
+
+/// Records a command on the transcript of the session running in the current task.
+pub fn trace_sent(line: &str) {
+    let _ = CURRENT.try_with(|log| log.sent(line));
+}
+
+/// Records a reply on the transcript of the session running in the current task, one entry per line.
+pub fn trace_received(reply: &str) {
+    let _ = CURRENT.try_with(|log| reply.lines().for_each(|line| log.received(line.trim_end())));
+}
+
+pub fn trace_info(message: &str) {
+    let _ = CURRENT.try_with(|log| log.info(message));
+}
+
+pub fn trace_error(message: &str) {
+    let _ = CURRENT.try_with(|log| log.error(message));
+}
+
+fn mask_secret(line: &str) -> String {
+    let verb = line.split(' ').next().unwrap_or_default();
+    if MASKED_COMMANDS.iter().any(|masked| verb.eq_ignore_ascii_case(masked)) && line.len() > verb.len() {
+        format!("{} ****", verb)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(log: &SessionLog) -> Vec<(LogDirection, String)> {
+        log.entries().into_iter().map(|entry| (entry.direction, entry.message)).collect()
+    }
+
+    #[tokio::test]
+    async fn traffic_goes_to_the_scoped_transcript() {
+        let log = SessionLog::new("session".to_string(), Arc::new(PendingPrompts::default()));
+        let other = SessionLog::new("other".to_string(), Arc::new(PendingPrompts::default()));
+        log.scope(async {
+            trace_sent("USER alice");
+            trace_sent("PASS s3cret");
+            trace_received("211-Features:\r\n UTF8\r\n211 End\r\n");
+            trace_error("Connection reset");
+        }).await;
+        trace_sent("NOOP");
+
+        assert_eq!(lines(&log), vec![
+            (LogDirection::Sent, "USER alice".to_string()),
+            (LogDirection::Sent, "PASS ****".to_string()),
+            (LogDirection::Received, "211-Features:".to_string()),
+            (LogDirection::Received, " UTF8".to_string()),
+            (LogDirection::Received, "211 End".to_string()),
+            (LogDirection::Error, "Connection reset".to_string()),
+        ]);
+        assert!(other.entries().is_empty());
+    }
+}
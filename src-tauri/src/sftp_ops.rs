@@ -1,44 +1,154 @@
-use ssh2::Session;
+use ssh2::{ErrorCode, KeyboardInteractivePrompt, MethodType, Prompt, Session, Sftp};
 use std::net::TcpStream;
 use std::path::Path;
-use std::time;
+use std::sync::Arc;
 use std::io::Write;
-use crate::models::FileItem;
+use crate::models::{ConnectionInfo, FileItem};
 use chrono::{DateTime, Utc};
 use crate::utils::format_bytes;
+use crate::prompts::PendingPrompts;
+use crate::host_keys::verify_host_key;
+use crate::net::normalize_host;
+use crate::proxy::connect_tcp;
+use crate::session_log::SessionLog;
+use crate::ssh_exec;
+use crate::ssh_tunnel::tunnel_channel;
+
+#[derive(Clone, serde::Serialize)]
+struct AuthPromptField {
+    text: String,
+    echo: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct AuthPromptRequest {
+    request_id: String,
+    host: String,
+    username: String,
+    instructions: String,
+    prompts: Vec<AuthPromptField>,
+}
+
+struct InteractivePrompter<'a> {
+    prompts: &'a PendingPrompts,
+    host: String,
+    password: Option<String>,
+    cancelled: bool,
+}
+
+impl KeyboardInteractivePrompt for InteractivePrompter<'_> {
+    fn prompt<'b>(&mut self, username: &str, instructions: &str, prompts: &[Prompt<'b>]) -> Vec<String> {
+        let mut responses: Vec<Option<String>> = prompts.iter()
+            .map(|p| {
+                if !p.echo && p.text.to_lowercase().contains("password") {
+                    self.password.take()
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let pending: Vec<usize> = (0..prompts.len()).filter(|&i| responses[i].is_none()).collect();
+        if !pending.is_empty() {
+            let request = AuthPromptRequest {
+                request_id: uuid::Uuid::new_v4().to_string(),
+                host: self.host.clone(),
+                username: username.to_string(),
+                instructions: instructions.to_string(),
+                prompts: pending.iter()
+                    .map(|&i| AuthPromptField { text: prompts[i].text.to_string(), echo: prompts[i].echo })
+                    .collect(),
+            };
+
+            match self.prompts.ask("auth-prompt", &request.request_id.clone(), request) {
+                Some(answers) => {
+                    for (&i, answer) in pending.iter().zip(answers) {
+                        responses[i] = Some(answer);
+                    }
+                }
+                None => self.cancelled = true,
+            }
+        }
 
+        responses.into_iter().map(|r| r.unwrap_or_default()).collect()
+    }
+}
+
+/// An SSH session used for file access. Servers without the SFTP subsystem are still usable:
+/// transfers then go over SCP and other operations run shell commands on an exec channel.
+#[derive(Clone)]
 pub struct SftpClient {
     session: Session,
-    _tcp: TcpStream,
+    sftp: Option<Arc<Sftp>>,
+    log: Arc<SessionLog>,
+    _tcp: Arc<TcpStream>,
 }
 
 impl SftpClient {
-    pub fn connect(host: &str, port: u16, username: &str, password: &str) -> Result<Self, String> {
-        let tcp = TcpStream::connect(format!("{}:{}", host, port))
-            .map_err(|e| format!("Failed to connect to {}:{}: {}", host, port, e))?;
-        tcp.set_read_timeout(Some(time::Duration::from_secs(30)))
-            .map_err(|e| format!("Failed to set read timeout: {}", e))?;
-        tcp.set_write_timeout(Some(time::Duration::from_secs(30)))
-            .map_err(|e| format!("Failed to set write timeout: {}", e))?;
-
-        let mut session = Session::new()
-            .map_err(|e| format!("Failed to create SSH session: {}", e))?;
-
-        session.set_tcp_stream(tcp.try_clone().map_err(|e| format!("Failed to clone TCP stream: {}", e))?);
-        
-        session.handshake()
-            .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    pub fn connect(conn_info: &ConnectionInfo, prompts: &PendingPrompts, log: Arc<SessionLog>) -> Result<Self, String> {
+        let jump_hosts = &conn_info.options.jump_hosts;
+        let (first_host, first_port) = match jump_hosts.first() {
+            Some(hop) => (hop.host.as_str(), hop.port),
+            None => (conn_info.host.as_str(), conn_info.port),
+        };
+        let mut tcp = connect_tcp(conn_info.options.proxy.as_ref(), first_host, first_port, conn_info.options.connect_timeout())?;
+
+        for (i, hop) in jump_hosts.iter().enumerate() {
+            let (next_host, next_port) = match jump_hosts.get(i + 1) {
+                Some(next) => (next.host.as_str(), next.port),
+                None => (conn_info.host.as_str(), conn_info.port),
+            };
+            let mut hop_info = hop.connection_info();
+            hop_info.options.read_timeout_secs = conn_info.options.read_timeout_secs;
+            hop_info.options.keepalive_interval_secs = conn_info.options.keepalive_interval_secs;
+            let hop_session = open_session(&tcp, &hop_info, prompts, &log)
+                .map_err(|e| format!("Jump host {}:{}: {}", hop.host, hop.port, e))?;
+            let channel = hop_session.channel_direct_tcpip(&normalize_host(next_host), next_port, None)
+                .map_err(|e| format!("Jump host {}:{} could not reach {}:{}: {}", hop.host, hop.port, next_host, next_port, e))?;
+            tcp = tunnel_channel(hop_session, channel)?;
+        }
+
+        let session = open_session(&tcp, conn_info, prompts, &log)?;
 
-        session.userauth_password(username, password)
-            .map_err(|e| format!("Authentication failed: {}", e))?;
+        let sftp = match session.sftp() {
+            Ok(sftp) => Some(Arc::new(sftp)),
+            Err(e) => {
+                log.error(format!("SFTP subsystem unavailable on {}:{} ({}), falling back to SCP and shell commands", conn_info.host, conn_info.port, e));
+                None
+            }
+        };
+
+        Ok(SftpClient { session, sftp, log, _tcp: Arc::new(tcp) })
+    }
+
+    pub fn keepalive(&self) -> bool {
+        self.session.keepalive_send().is_ok()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        match &self.sftp {
+            Some(sftp) => sftp.realpath(Path::new(".")).is_ok(),
+            None => ssh_exec::is_alive(&self.session),
+        }
+    }
 
-        Ok(SftpClient { session, _tcp: tcp })
+    pub fn disconnect(&self) {
+        let _ = self.session.disconnect(None, "Closed by user", None);
+    }
+
+    /// Records the status code of a failed SFTP request on the session's transcript.
+    fn record_status(&self, e: ssh2::Error) -> ssh2::Error {
+        if let ErrorCode::SFTP(status) = e.code() {
+            self.log.received(&format!("SFTP status {} ({}): {}", status, sftp_status_name(status), e.message()));
+        }
+        e
     }
 
     
     pub fn list_directory(&self, path: &str) -> Result<Vec<FileItem>, String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::list_directory(&self.session, path);
+        };
 
         let mut items = Vec::new();
         
@@ -50,7 +160,7 @@ impl SftpClient {
                 }
             },
             Err(e) => {
-                return Err(format!("Failed to access path '{}': {}", path, e));
+                return Err(format!("Failed to access path '{}': {}", path, self.record_status(e)));
             }
         }
         
@@ -103,7 +213,7 @@ impl SftpClient {
                 Ok(items)
             }
             Err(e) => {
-                let error_msg = e.to_string();
+                let error_msg = self.record_status(e).to_string();
                 if error_msg.contains("invalid syntax") || error_msg.contains("protocol error") {
                     Err(format!("SFTP server protocol error: {}. The server may have compatibility issues. Try using FTP instead.", error_msg))
                 } else {
@@ -114,54 +224,59 @@ impl SftpClient {
     }
 
     pub fn create_directory(&self, path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::create_directory(&self.session, path);
+        };
 
         sftp.mkdir(Path::new(path), 0o755)
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
+            .map_err(|e| format!("Failed to create directory: {}", self.record_status(e)))?;
 
         Ok(())
     }
 
     pub fn remove_file(&self, path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::remove_file(&self.session, path);
+        };
 
         sftp.unlink(Path::new(path))
-            .map_err(|e| format!("Failed to remove file: {}", e))?;
+            .map_err(|e| format!("Failed to remove file: {}", self.record_status(e)))?;
 
         Ok(())
     }
 
     pub fn remove_directory(&self, path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::remove_directory(&self.session, path);
+        };
 
         sftp.rmdir(Path::new(path))
-            .map_err(|e| format!("Failed to remove directory: {}", e))?;
+            .map_err(|e| format!("Failed to remove directory: {}", self.record_status(e)))?;
 
         Ok(())
     }
 
     pub fn rename_file(&self, src: &str, dst: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::rename_file(&self.session, src, dst);
+        };
 
         sftp.rename(Path::new(src), Path::new(dst), None)
-            .map_err(|e| format!("Failed to rename file: {}", e))?;
+            .map_err(|e| format!("Failed to rename file: {}", self.record_status(e)))?;
 
         Ok(())
     }
 
     pub fn upload_file(&self, local_path: &str, remote_path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::upload_file(&self.session, local_path, remote_path);
+        };
 
         let mut local_file = std::fs::File::open(local_path)
             .map_err(|e| format!("Failed to open local file: {}", e))?;
 
         let mut remote_file = sftp.create(Path::new(remote_path))
-            .map_err(|e| format!("Failed to create remote file: {}", e))?;
+            .map_err(|e| format!("Failed to create remote file: {}", self.record_status(e)))?;
 
         std::io::copy(&mut local_file, &mut remote_file)
             .map_err(|e| format!("Failed to copy file content: {}", e))?;
@@ -170,8 +285,9 @@ impl SftpClient {
     }
 
     pub fn download_file(&self, remote_path: &str, local_path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::download_file(&self.session, remote_path, local_path);
+        };
 
         let remote_path_obj = Path::new(remote_path);
         match sftp.stat(remote_path_obj) {
@@ -181,13 +297,13 @@ impl SftpClient {
                 }
             },
             Err(e) => {
-                return Err(format!("Remote file '{}' does not exist or cannot be accessed: {}", remote_path, e));
+                return Err(format!("Remote file '{}' does not exist or cannot be accessed: {}", remote_path, self.record_status(e)));
             }
         }
 
         let mut remote_file = sftp.open(remote_path_obj)
             .map_err(|e| {
-                let error_msg = e.to_string();
+                let error_msg = self.record_status(e).to_string();
                 if error_msg.contains("invalid syntax") || error_msg.contains("protocol error") {
                     format!("SFTP server protocol error while opening file '{}': {}. The server may have compatibility issues.", remote_path, error_msg)
                 } else {
@@ -223,11 +339,12 @@ impl SftpClient {
     }
 
     pub fn create_empty_file(&self, path: &str) -> Result<(), String> {
-        let sftp = self.session.sftp()
-            .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        let Some(sftp) = &self.sftp else {
+            return ssh_exec::create_empty_file(&self.session, path);
+        };
 
         let mut file = sftp.create(Path::new(path))
-            .map_err(|e| format!("Failed to create remote file: {}", e))?;
+            .map_err(|e| format!("Failed to create remote file: {}", self.record_status(e)))?;
         use std::io::Write;
         file.write_all(b"")
             .map_err(|e| format!("Failed to write empty content: {}", e))?;
@@ -235,3 +352,162 @@ impl SftpClient {
         Ok(())
     }
 }
+
+fn open_session(tcp: &TcpStream, conn_info: &ConnectionInfo, prompts: &PendingPrompts, log: &SessionLog) -> Result<Session, String> {
+    let read_timeout = conn_info.options.read_timeout();
+    tcp.set_read_timeout(Some(read_timeout))
+        .map_err(|e| format!("Failed to set read timeout: {}", e))?;
+    tcp.set_write_timeout(Some(read_timeout))
+        .map_err(|e| format!("Failed to set write timeout: {}", e))?;
+
+    let mut session = Session::new()
+        .map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    session.set_timeout(read_timeout.as_millis() as u32);
+    if let Some(interval) = conn_info.options.keepalive_interval() {
+        session.set_keepalive(false, interval.as_secs() as u32);
+    }
+
+    session.set_tcp_stream(tcp.try_clone().map_err(|e| format!("Failed to clone TCP stream: {}", e))?);
+
+    session.handshake()
+        .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    log_handshake(&session, conn_info, log);
+
+    verify_host_key(&session, &conn_info.host, conn_info.port, prompts)?;
+
+    let method = authenticate(&session, conn_info, prompts, log)?;
+    log.info(format!("Authenticated as {} with {}", conn_info.username, method));
+
+    Ok(session)
+}
+
+fn log_handshake(session: &Session, conn_info: &ConnectionInfo, log: &SessionLog) {
+    if let Some(banner) = session.banner() {
+        log.received(banner);
+    }
+    let method = |method_type| session.methods(method_type).unwrap_or("unknown");
+    log.info(format!(
+        "SSH handshake with {}: key exchange {}, host key {}, cipher {}, MAC {}",
+        conn_info.host, method(MethodType::Kex), method(MethodType::HostKey), method(MethodType::CryptCs), method(MethodType::MacCs),
+    ));
+}
+
+/// Tries each configured way of logging in and returns the name of the one that worked.
+fn authenticate(session: &Session, conn_info: &ConnectionInfo, prompts: &PendingPrompts, log: &SessionLog) -> Result<&'static str, String> {
+    let username = &conn_info.username;
+    let options = &conn_info.options;
+    let mut errors = Vec::new();
+
+    if options.agent_only {
+        return authenticate_with_agent(session, username)
+            .map(|_| "ssh-agent")
+            .map_err(|e| format!("Authentication failed: ssh-agent: {}", e));
+    }
+
+    if let Some(key_path) = options.private_key_path.as_deref().filter(|p| !p.is_empty()) {
+        let private_key = Path::new(key_path);
+        let public_key_path = format!("{}.pub", key_path);
+        let public_key = Path::new(&public_key_path);
+        let public_key = if public_key.exists() { Some(public_key) } else { None };
+
+        match session.userauth_pubkey_file(username, public_key, private_key, options.key_passphrase.as_deref()) {
+            Ok(_) => return Ok("public key"),
+            Err(e) => errors.push(format!("public key '{}': {}", key_path, e)),
+        }
+    }
+
+    if options.use_agent {
+        match authenticate_with_agent(session, username) {
+            Ok(_) => return Ok("ssh-agent"),
+            Err(e) => errors.push(format!("ssh-agent: {}", e)),
+        }
+    }
+
+    let methods = session.auth_methods(username).unwrap_or("").to_string();
+    log.info(format!("Server offers authentication methods: {}", if methods.is_empty() { "none listed" } else { &methods }));
+    if methods.contains("keyboard-interactive") {
+        let mut prompter = InteractivePrompter {
+            prompts,
+            host: conn_info.host.clone(),
+            password: Some(conn_info.password.clone()).filter(|p| !p.is_empty()),
+            cancelled: false,
+        };
+        match session.userauth_keyboard_interactive(username, &mut prompter) {
+            Ok(_) if session.authenticated() => return Ok("keyboard-interactive"),
+            Ok(_) => errors.push("keyboard-interactive: further authentication required".to_string()),
+            Err(_) if prompter.cancelled => errors.push("keyboard-interactive: cancelled by user".to_string()),
+            Err(e) => errors.push(format!("keyboard-interactive: {}", e)),
+        }
+    }
+
+    if !conn_info.password.is_empty() && (methods.is_empty() || methods.contains("password")) {
+        match session.userauth_password(username, &conn_info.password) {
+            Ok(_) => return Ok("password"),
+            Err(e) => errors.push(format!("password: {}", e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Err("Authentication failed: no credentials provided".to_string())
+    } else {
+        Err(format!("Authentication failed: {}", errors.join("; ")))
+    }
+}
+
+fn authenticate_with_agent(session: &Session, username: &str) -> Result<(), String> {
+    let mut agent = session.agent()
+        .map_err(|e| format!("Failed to initialize agent: {}", e))?;
+    agent.connect()
+        .map_err(|e| format!("Failed to connect to agent: {}", e))?;
+    agent.list_identities()
+        .map_err(|e| format!("Failed to list agent identities: {}", e))?;
+
+    let identities = agent.identities()
+        .map_err(|e| format!("Failed to read agent identities: {}", e))?;
+    if identities.is_empty() {
+        let _ = agent.disconnect();
+        return Err("agent has no identities".to_string());
+    }
+
+    let mut last_error = String::new();
+    for identity in &identities {
+        match agent.userauth(username, identity) {
+            Ok(_) => {
+                let _ = agent.disconnect();
+                return Ok(());
+            }
+            Err(e) => last_error = format!("'{}': {}", identity.comment(), e),
+        }
+    }
+
+    let _ = agent.disconnect();
+    Err(format!("none of {} identities accepted (last {})", identities.len(), last_error))
+}
+
+/// Names of the SFTP status codes libssh2 reports.
+fn sftp_status_name(status: i32) -> &'static str {
+    match status {
+        1 => "end of file",
+        2 => "no such file",
+        3 => "permission denied",
+        4 => "failure",
+        5 => "bad message",
+        6 => "no connection",
+        7 => "connection lost",
+        8 => "operation unsupported",
+        9 => "invalid handle",
+        10 => "no such path",
+        11 => "file already exists",
+        12 => "write protected",
+        13 => "no media",
+        14 => "no space on filesystem",
+        15 => "quota exceeded",
+        16 => "unknown principal",
+        17 => "lock conflict",
+        18 => "directory not empty",
+        19 => "not a directory",
+        20 => "invalid filename",
+        21 => "link loop",
+        _ => "unknown status",
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
+use crate::models::{ConnectionOptions, ConnectionProtocol};
+use crate::sites::{SiteAuthMethod, SiteProfile};
+use crate::utils::percent_decode;
+
+#[derive(Serialize, Clone)]
+pub struct ImportIssue {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    pub sites: Vec<SiteProfile>,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    fn issue(&mut self, name: &str, reason: impl Into<String>) {
+        self.issues.push(ImportIssue { name: name.to_string(), reason: reason.into() });
+    }
+}
+
+fn default_port(protocol: &ConnectionProtocol) -> u16 {
+    match protocol {
+        ConnectionProtocol::SFTP => 22,
+        ConnectionProtocol::FTPSImplicit => 990,
+        ConnectionProtocol::WebDAV => 80,
+        ConnectionProtocol::WebDAVS | ConnectionProtocol::S3 => 443,
+        _ => 21,
+    }
+}
+
+fn new_site(name: &str, group: Option<String>, host: &str, protocol: ConnectionProtocol) -> SiteProfile {
+    SiteProfile {
+        id: String::new(),
+        name: name.to_string(),
+        group,
+        host: host.to_string(),
+        port: default_port(&protocol),
+        username: None,
+        password: None,
+        protocol: Some(protocol),
+        auth_method: SiteAuthMethod::Password,
+        remote_dir: None,
+        local_dir: None,
+        options: ConnectionOptions::default(),
+    }
+}
+
+// FileZilla sitemanager.xml
+
+pub fn parse_filezilla(xml: &str) -> Result<ImportReport, String> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| format!("Failed to parse FileZilla site manager file: {}", e))?;
+    let servers = doc.descendants()
+        .find(|n| n.has_tag_name("Servers"))
+        .ok_or("No <Servers> element found; is this a FileZilla sitemanager.xml?")?;
+
+    let mut report = ImportReport::default();
+    walk_filezilla_folder(servers, &[], &mut report);
+    Ok(report)
+}
+
+fn walk_filezilla_folder(node: roxmltree::Node, folders: &[String], report: &mut ImportReport) {
+    for child in node.children().filter(|n| n.is_element()) {
+        if child.has_tag_name("Folder") {
+            let name = child.children()
+                .filter(|n| n.is_text())
+                .filter_map(|n| n.text())
+                .map(str::trim)
+                .find(|t| !t.is_empty())
+                .unwrap_or("Unnamed folder")
+                .to_string();
+            let mut path = folders.to_vec();
+            path.push(name);
+            walk_filezilla_folder(child, &path, report);
+        } else if child.has_tag_name("Server") {
+            let group = if folders.is_empty() { None } else { Some(folders.join("/")) };
+            convert_filezilla_server(child, group, report);
+        }
+    }
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, 'a>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+fn convert_filezilla_server(server: roxmltree::Node, group: Option<String>, report: &mut ImportReport) {
+    let host = child_text(server, "Host").unwrap_or("");
+    let name = child_text(server, "Name").unwrap_or(host).to_string();
+    if host.is_empty() {
+        report.issue(&name, "Entry has no host");
+        return;
+    }
+
+    let protocol = match child_text(server, "Protocol").unwrap_or("0") {
+        "0" | "6" => ConnectionProtocol::FTP,
+        "1" => ConnectionProtocol::SFTP,
+        "3" => ConnectionProtocol::FTPSImplicit,
+        "4" => ConnectionProtocol::FTPS,
+        other => {
+            report.issue(&name, format!("Unsupported FileZilla protocol {}", other));
+            return;
+        }
+    };
+
+    let mut site = new_site(&name, group, host, protocol);
+    if let Some(port) = child_text(server, "Port") {
+        match port.parse() {
+            Ok(port) => site.port = port,
+            Err(_) => report.issue(&name, format!("Invalid port '{}', using {}", port, site.port)),
+        }
+    }
+    site.username = child_text(server, "User").map(str::to_string);
+    site.remote_dir = child_text(server, "RemoteDir").and_then(decode_filezilla_path);
+    site.local_dir = child_text(server, "LocalDir").map(str::to_string);
+
+    if let Some(pass) = server.children().find(|n| n.has_tag_name("Pass")) {
+        match pass.attribute("encoding") {
+            Some("base64") => match pass.text().map(|t| STANDARD.decode(t.trim())) {
+                Some(Ok(bytes)) => site.password = String::from_utf8(bytes).ok(),
+                _ => report.issue(&name, "Password could not be decoded and was not imported"),
+            },
+            Some("crypt") => report.issue(&name, "Password is protected by a FileZilla master password and was not imported"),
+            _ => site.password = pass.text().map(str::to_string),
+        }
+    }
+
+    site.auth_method = match child_text(server, "Logontype").unwrap_or("1") {
+        "0" => SiteAuthMethod::Anonymous,
+        "3" => SiteAuthMethod::KeyboardInteractive,
+        "5" => {
+            site.options.private_key_path = child_text(server, "Keyfile").map(str::to_string);
+            if site.options.private_key_path.is_none() {
+                report.issue(&name, "Key file logon without a key file; imported as password logon");
+                SiteAuthMethod::Password
+            } else {
+                SiteAuthMethod::PrivateKey
+            }
+        }
+        "4" => {
+            report.issue(&name, "FTP account logon is not supported; the account was dropped");
+            SiteAuthMethod::Password
+        }
+        _ => SiteAuthMethod::Password,
+    };
+
+    report.sites.push(site);
+}
+
+/// Decodes FileZilla's serialized server path, e.g. `1 0 4 home 3 www` becomes `/home/www`.
+/// The format is `<server type> <prefix length> [prefix] (<segment length> <segment>)*`.
+fn decode_filezilla_path(encoded: &str) -> Option<String> {
+    fn take_number(rest: &mut &str) -> Option<usize> {
+        let trimmed = rest.trim_start();
+        let end = trimmed.find(' ').unwrap_or(trimmed.len());
+        let number = trimmed[..end].parse().ok()?;
+        *rest = &trimmed[end..];
+        Some(number)
+    }
+    fn take_chars<'a>(rest: &mut &'a str, count: usize) -> Option<&'a str> {
+        let trimmed = rest.strip_prefix(' ')?;
+        let end = match trimmed.char_indices().nth(count) {
+            Some((i, _)) => i,
+            None if trimmed.chars().count() == count => trimmed.len(),
+            None => return None,
+        };
+        let value = &trimmed[..end];
+        *rest = &trimmed[end..];
+        Some(value)
+    }
+
+    let mut rest = encoded;
+    let _server_type = take_number(&mut rest)?;
+    let prefix_len = take_number(&mut rest)?;
+    let prefix = if prefix_len > 0 { take_chars(&mut rest, prefix_len)? } else { "" };
+
+    let mut segments = Vec::new();
+    while !rest.trim().is_empty() {
+        let len = take_number(&mut rest)?;
+        segments.push(take_chars(&mut rest, len)?);
+    }
+
+    Some(format!("{}/{}", prefix, segments.join("/")))
+}
+
+// WinSCP.ini
+
+/// Reverses WinSCP's password obfuscation (the non-master-password case).
+fn decode_winscp_password(host: &str, username: &str, encoded: &str) -> Option<String> {
+    const MAGIC: u8 = 0xA3;
+    const FLAG: u8 = 0xFF;
+
+    let mut bytes = (0..encoded.len() / 2)
+        .map(|i| u8::from_str_radix(&encoded[i * 2..i * 2 + 2], 16).ok().map(|b| !(b ^ MAGIC)))
+        .collect::<Option<Vec<u8>>>()?
+        .into_iter();
+
+    let flag = bytes.next()?;
+    let length = if flag == FLAG {
+        bytes.next()?;
+        bytes.next()? as usize
+    } else {
+        flag as usize
+    };
+    let skip = bytes.next()? as usize;
+    let decoded: Vec<u8> = bytes.skip(skip).take(length).collect();
+    if decoded.len() != length {
+        return None;
+    }
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    if flag == FLAG {
+        let key = format!("{}{}", username, host);
+        decoded.strip_prefix(&key).map(str::to_string)
+    } else {
+        Some(decoded)
+    }
+}
+
+pub fn parse_winscp(ini: &str) -> ImportReport {
+    let mut sessions: Vec<(String, HashMap<String, String>)> = Vec::new();
+    let mut current: Option<(String, HashMap<String, String>)> = None;
+    let mut master_password_set = false;
+
+    for line in ini.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sessions.extend(current.take());
+            if let Some(name) = section.strip_prefix("Sessions\\") {
+                current = Some((percent_decode(name), HashMap::new()));
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key == "UseMasterPassword" && value == "1" {
+                master_password_set = true;
+            }
+            if let Some((_, values)) = current.as_mut() {
+                values.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    sessions.extend(current);
+
+    let mut report = ImportReport::default();
+    for (full_name, values) in sessions {
+        if full_name == "Default Settings" {
+            continue;
+        }
+        let (group, name) = match full_name.rsplit_once('/') {
+            Some((group, name)) => (Some(group.to_string()), name.to_string()),
+            None => (None, full_name.clone()),
+        };
+        convert_winscp_session(&name, group, &values, master_password_set, &mut report);
+    }
+    report
+}
+
+fn convert_winscp_session(
+    name: &str,
+    group: Option<String>,
+    values: &HashMap<String, String>,
+    master_password_set: bool,
+    report: &mut ImportReport,
+) {
+    let get = |key: &str| values.get(key).map(|v| percent_decode(v)).filter(|v| !v.is_empty());
+
+    let Some(host) = get("HostName") else {
+        report.issue(name, "Session has no host name");
+        return;
+    };
+
+    let protocol = match (get("FSProtocol").as_deref().unwrap_or("2"), get("Ftps").as_deref().unwrap_or("0")) {
+        ("0" | "1" | "2", _) => ConnectionProtocol::SFTP,
+        ("5", "1") => ConnectionProtocol::FTPSImplicit,
+        ("5", "2" | "3") => ConnectionProtocol::FTPS,
+        ("5", _) => ConnectionProtocol::FTP,
+        ("6", "0") => ConnectionProtocol::WebDAV,
+        ("6", _) => ConnectionProtocol::WebDAVS,
+        ("7", _) => ConnectionProtocol::S3,
+        (other, _) => {
+            report.issue(name, format!("Unsupported WinSCP protocol {} session", other));
+            return;
+        }
+    };
+
+    let mut site = new_site(name, group, &host, protocol);
+    if let Some(port) = get("PortNumber") {
+        match port.parse() {
+            Ok(port) => site.port = port,
+            Err(_) => report.issue(name, format!("Invalid port '{}', using {}", port, site.port)),
+        }
+    }
+    site.username = get("UserName");
+    site.remote_dir = get("RemoteDirectory");
+    site.local_dir = get("LocalDirectory");
+
+    if let Some(encoded) = get("Password") {
+        if master_password_set {
+            report.issue(name, "Password is protected by a WinSCP master password and was not imported");
+        } else {
+            site.password = decode_winscp_password(&host, site.username.as_deref().unwrap_or(""), &encoded);
+            if site.password.is_none() {
+                report.issue(name, "Password could not be decoded and was not imported");
+            }
+        }
+    }
+
+    if let Some(key_file) = get("PublicKeyFile") {
+        if key_file.to_lowercase().ends_with(".ppk") {
+            report.issue(name, "PuTTY .ppk key files must be converted to OpenSSH format before use");
+        }
+        site.options.private_key_path = Some(key_file);
+        site.auth_method = SiteAuthMethod::PrivateKey;
+    }
+    site.options.use_agent = get("TryAgent").as_deref() != Some("0");
+    site.options.s3_region = get("S3DefaultRegion");
+
+    report.sites.push(site);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `S3cret!` for alice@ftp.example.com, obfuscated the way current WinSCP versions store it.
+    const WINSCP_FLAGGED: &str = "A35C475F4D7E6F3D30353F393A282C7239243D312C3039723F33310F6F3F2E39287D18181818";
+    /// `S3cret!` in the older form that stores the bare length and no host/user key.
+    const WINSCP_PLAIN_LENGTH: &str = "5B5E093A0F6F3F2E39287D2B2B2B2B2B2B";
+
+    #[test]
+    fn filezilla_path() {
+        assert_eq!(decode_filezilla_path("1 0 4 home 3 www").as_deref(), Some("/home/www"));
+        assert_eq!(decode_filezilla_path("1 0 8 my files").as_deref(), Some("/my files"));
+        assert_eq!(decode_filezilla_path("1 0").as_deref(), Some("/"));
+        assert_eq!(decode_filezilla_path("1 0 9 home"), None);
+    }
+
+    #[test]
+    fn winscp_password() {
+        assert_eq!(decode_winscp_password("ftp.example.com", "alice", WINSCP_FLAGGED).as_deref(), Some("S3cret!"));
+        assert_eq!(decode_winscp_password("ftp.example.com", "alice", WINSCP_PLAIN_LENGTH).as_deref(), Some("S3cret!"));
+        // The flagged form is keyed on user and host, so a different session can't decode it.
+        assert_eq!(decode_winscp_password("other.example.com", "alice", WINSCP_FLAGGED), None);
+        assert_eq!(decode_winscp_password("ftp.example.com", "alice", "A35C"), None);
+    }
+
+    #[test]
+    fn filezilla_nested_folders() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<FileZilla3>
+  <Servers>
+    <Server><Host>top.example.com</Host><Protocol>1</Protocol><Name>Top</Name></Server>
+    <Folder expanded="1">Clients
+      <Folder>Acme
+        <Server>
+          <Host>ftp.acme.example</Host>
+          <Port>2121</Port>
+          <Protocol>4</Protocol>
+          <Logontype>1</Logontype>
+          <User>acme</User>
+          <Pass encoding="base64">aHVudGVyMg==</Pass>
+          <Name>Acme FTPS</Name>
+          <RemoteDir>1 0 4 home 3 www</RemoteDir>
+        </Server>
+      </Folder>
+    </Folder>
+  </Servers>
+</FileZilla3>"#;
+        let report = parse_filezilla(xml).unwrap();
+        assert!(report.issues.is_empty());
+        assert_eq!(report.sites.len(), 2);
+
+        let top = &report.sites[0];
+        assert_eq!((top.group.as_deref(), top.port), (None, 22));
+
+        let acme = &report.sites[1];
+        assert_eq!(acme.name, "Acme FTPS");
+        assert_eq!(acme.group.as_deref(), Some("Clients/Acme"));
+        assert!(matches!(acme.protocol, Some(ConnectionProtocol::FTPS)));
+        assert_eq!(acme.port, 2121);
+        assert_eq!(acme.password.as_deref(), Some("hunter2"));
+        assert_eq!(acme.remote_dir.as_deref(), Some("/home/www"));
+    }
+
+    #[test]
+    fn filezilla_master_password_is_reported() {
+        let xml = r#"<FileZilla3><Servers><Server>
+            <Host>locked.example.com</Host><User>bob</User><Pass encoding="crypt" pubkey="abc">def</Pass><Name>Locked</Name>
+        </Server></Servers></FileZilla3>"#;
+        let report = parse_filezilla(xml).unwrap();
+        assert_eq!(report.sites.len(), 1);
+        assert_eq!(report.sites[0].password, None);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].name, "Locked");
+        assert!(report.issues[0].reason.contains("master password"));
+    }
+
+    #[test]
+    fn winscp_sessions() {
+        let ini = format!(r"[Configuration\Interface]
+Theme=dark
+
+[Sessions\Default%20Settings]
+HostName=ignored.example.com
+
+[Sessions\Work/Alice%20FTP]
+HostName=ftp.example.com
+UserName=alice
+FSProtocol=5
+Ftps=3
+PortNumber=990x
+Password={}
+RemoteDirectory=/var/www
+
+[Sessions\Legacy]
+HostName=legacy.example.com
+UserName=carol
+Password={}
+", WINSCP_FLAGGED, WINSCP_PLAIN_LENGTH);
+        let report = parse_winscp(&ini);
+        assert_eq!(report.sites.len(), 2);
+
+        let alice = &report.sites[0];
+        assert_eq!(alice.name, "Alice FTP");
+        assert_eq!(alice.group.as_deref(), Some("Work"));
+        assert!(matches!(alice.protocol, Some(ConnectionProtocol::FTPS)));
+        assert_eq!(alice.port, 21);
+        assert_eq!(alice.password.as_deref(), Some("S3cret!"));
+        assert_eq!(alice.remote_dir.as_deref(), Some("/var/www"));
+
+        let legacy = &report.sites[1];
+        assert!(matches!(legacy.protocol, Some(ConnectionProtocol::SFTP)));
+        assert_eq!(legacy.password.as_deref(), Some("S3cret!"));
+
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].reason.contains("Invalid port '990x'"));
+    }
+
+    #[test]
+    fn winscp_master_password_is_reported() {
+        let ini = format!("[Configuration\\Security]\nUseMasterPassword=1\n\n[Sessions\\Locked]\nHostName=ftp.example.com\nUserName=alice\nPassword={}\n", WINSCP_FLAGGED);
+        let report = parse_winscp(&ini);
+        assert_eq!(report.sites[0].password, None);
+        assert!(report.issues[0].reason.contains("master password"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use tauri::{AppHandle, Manager};
+use crate::models::{ConnectionInfo, ConnectionOptions, ConnectionProtocol};
+use crate::net::normalize_host;
+use crate::vault::VaultEntry;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SiteAuthMethod {
+    #[default]
+    Password,
+    Anonymous,
+    PrivateKey,
+    Agent,
+    KeyboardInteractive,
+}
+
+impl SiteAuthMethod {
+    pub fn uses_secret(&self) -> bool {
+        matches!(self, SiteAuthMethod::Password | SiteAuthMethod::PrivateKey | SiteAuthMethod::KeyboardInteractive)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SiteProfile {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub group: Option<String>,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// `None` means the protocol is auto-detected on connect.
+    #[serde(default)]
+    pub protocol: Option<ConnectionProtocol>,
+    #[serde(default)]
+    pub auth_method: SiteAuthMethod,
+    #[serde(default)]
+    pub remote_dir: Option<String>,
+    #[serde(default)]
+    pub local_dir: Option<String>,
+    #[serde(default)]
+    pub options: ConnectionOptions,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SitesFile {
+    #[serde(default)]
+    sites: Vec<SiteProfile>,
+}
+
+fn sites_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app.path().app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    Ok(config_dir.join("sites.json"))
+}
+
+pub fn load_sites(app: &AppHandle) -> Result<Vec<SiteProfile>, String> {
+    let path = sites_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read site manager file: {}", e))?;
+    let file: SitesFile = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse site manager file: {}", e))?;
+    Ok(file.sites)
+}
+
+pub fn save_sites(app: &AppHandle, sites: Vec<SiteProfile>) -> Result<(), String> {
+    let path = sites_path(app)?;
+    let content = serde_json::to_string_pretty(&SitesFile { sites })
+        .map_err(|e| format!("Failed to serialize site manager file: {}", e))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write site manager file: {}", e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to write site manager file: {}", e))
+}
+
+pub fn find_site(app: &AppHandle, id: &str) -> Result<SiteProfile, String> {
+    load_sites(app)?
+        .into_iter()
+        .find(|site| site.id == id)
+        .ok_or_else(|| format!("No saved site with id '{}'", id))
+}
+
+pub fn upsert_site(app: &AppHandle, mut site: SiteProfile) -> Result<SiteProfile, String> {
+    if site.name.trim().is_empty() {
+        return Err("Site name cannot be empty".to_string());
+    }
+    if site.host.trim().is_empty() {
+        return Err("Site host cannot be empty".to_string());
+    }
+
+    let mut sites = load_sites(app)?;
+    if site.id.is_empty() {
+        site.id = uuid::Uuid::new_v4().to_string();
+        sites.push(site.clone());
+    } else {
+        let existing = sites.iter_mut()
+            .find(|s| s.id == site.id)
+            .ok_or_else(|| format!("No saved site with id '{}'", site.id))?;
+        *existing = site.clone();
+    }
+
+    save_sites(app, sites)?;
+    Ok(site)
+}
+
+pub fn delete_site(app: &AppHandle, id: &str) -> Result<(), String> {
+    let mut sites = load_sites(app)?;
+    let before = sites.len();
+    sites.retain(|site| site.id != id);
+    if sites.len() == before {
+        return Err(format!("No saved site with id '{}'", id));
+    }
+    save_sites(app, sites)
+}
+
+pub fn set_site_group(app: &AppHandle, id: &str, group: Option<String>) -> Result<(), String> {
+    let mut sites = load_sites(app)?;
+    let site = sites.iter_mut()
+        .find(|site| site.id == id)
+        .ok_or_else(|| format!("No saved site with id '{}'", id))?;
+    site.group = group.filter(|g| !g.trim().is_empty());
+    save_sites(app, sites)
+}
+
+pub fn list_groups(app: &AppHandle) -> Result<Vec<String>, String> {
+    let mut groups: Vec<String> = load_sites(app)?
+        .into_iter()
+        .filter_map(|site| site.group)
+        .collect();
+    groups.sort();
+    groups.dedup();
+    Ok(groups)
+}
+
+pub fn connection_info(site: &SiteProfile, password: Option<String>, secrets: Option<VaultEntry>) -> Result<ConnectionInfo, String> {
+    let secrets = secrets.unwrap_or_default();
+    let mut options = site.options.clone();
+    if options.key_passphrase.is_none() {
+        options.key_passphrase = secrets.key_passphrase.clone();
+    }
+    if let Some(proxy) = options.proxy.as_mut().filter(|proxy| proxy.password.is_none()) {
+        proxy.password = secrets.proxy_password.clone();
+    }
+    for hop in options.jump_hosts.iter_mut() {
+        if let Some(saved) = secrets.jump_host(hop) {
+            hop.password = hop.password.take().or_else(|| saved.password.clone());
+            hop.key_passphrase = hop.key_passphrase.take().or_else(|| saved.key_passphrase.clone());
+        }
+    }
+
+    let (username, password) = match site.auth_method {
+        SiteAuthMethod::Anonymous => ("anonymous".to_string(), "anonymous@".to_string()),
+        _ => (
+            site.username.clone().unwrap_or_default(),
+            password.or(secrets.password).or_else(|| site.password.clone()).unwrap_or_default(),
+        ),
+    };
+
+    match site.auth_method {
+        SiteAuthMethod::PrivateKey if options.private_key_path.is_none() => {
+            return Err(format!("Site '{}' uses key authentication but has no private key path", site.name));
+        }
+        SiteAuthMethod::Agent => {
+            options.use_agent = true;
+            options.agent_only = true;
+        }
+        _ => {}
+    }
+
+    Ok(ConnectionInfo {
+        host: normalize_host(&site.host),
+        port: site.port,
+        username,
+        password,
+        protocol: site.protocol.clone().unwrap_or(ConnectionProtocol::FTP),
+        options,
+    })
+}
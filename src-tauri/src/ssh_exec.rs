@@ -0,0 +1,131 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use ssh2::Session;
+use crate::models::FileItem;
+use crate::utils::{format_bytes, parse_unix_list_line};
+
+/// Quotes `value` for a POSIX shell.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Runs `command` on an exec channel and returns its stdout; a non-zero exit becomes an error
+/// carrying stderr.
+fn exec(session: &Session, command: &str) -> Result<String, String> {
+    let mut channel = session.channel_session()
+        .map_err(|e| format!("Failed to open SSH channel: {}", e))?;
+    channel.exec(command)
+        .map_err(|e| format!("Failed to run '{}': {}", command, e))?;
+
+    let mut stdout = Vec::new();
+    channel.read_to_end(&mut stdout)
+        .map_err(|e| format!("Failed to read output of '{}': {}", command, e))?;
+    let mut stderr = String::new();
+    let _ = channel.stderr().read_to_string(&mut stderr);
+    let _ = channel.wait_close();
+
+    match channel.exit_status() {
+        Ok(0) => Ok(String::from_utf8_lossy(&stdout).into_owned()),
+        Ok(status) if stderr.trim().is_empty() => Err(format!("'{}' exited with status {}", command, status)),
+        Ok(_) => Err(stderr.trim().to_string()),
+        Err(e) => Err(format!("Failed to read exit status of '{}': {}", command, e)),
+    }
+}
+
+pub fn is_alive(session: &Session) -> bool {
+    exec(session, "true").is_ok()
+}
+
+pub fn list_directory(session: &Session, path: &str) -> Result<Vec<FileItem>, String> {
+    let output = exec(session, &format!("LC_ALL=C ls -la -- {}", quote(path)))
+        .map_err(|e| format!("Failed to list directory '{}': {}", path, e))?;
+
+    Ok(output.lines().filter_map(parse_unix_list_line).map(|(name, size, is_directory, date_str, permissions)| {
+        let full_path = if path == "/" { format!("/{}", name) } else { format!("{}/{}", path.trim_end_matches('/'), name) };
+        let size = if is_directory { 0 } else { size };
+        FileItem {
+            name,
+            full_path,
+            size,
+            modified: None,
+            is_directory,
+            readable_size: if is_directory { "".to_string() } else { format_bytes(size) },
+            readable_modified: date_str,
+            permissions,
+        }
+    }).collect())
+}
+
+pub fn create_directory(session: &Session, path: &str) -> Result<(), String> {
+    exec(session, &format!("mkdir -- {}", quote(path)))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to create directory: {}", e))
+}
+
+pub fn remove_file(session: &Session, path: &str) -> Result<(), String> {
+    exec(session, &format!("rm -- {}", quote(path)))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to remove file: {}", e))
+}
+
+pub fn remove_directory(session: &Session, path: &str) -> Result<(), String> {
+    exec(session, &format!("rmdir -- {}", quote(path)))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to remove directory: {}", e))
+}
+
+pub fn rename_file(session: &Session, src: &str, dst: &str) -> Result<(), String> {
+    exec(session, &format!("mv -- {} {}", quote(src), quote(dst)))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to rename file: {}", e))
+}
+
+fn scp_write(session: &Session, remote_path: &str, source: &mut dyn Read, size: u64) -> Result<(), String> {
+    let mut channel = session.scp_send(Path::new(remote_path), 0o644, size, None)
+        .map_err(|e| format!("Failed to start SCP upload: {}", e))?;
+    let copied = std::io::copy(source, &mut channel)
+        .map_err(|e| format!("Failed to copy file content: {}", e))?;
+    if copied != size {
+        return Err(format!("Local file changed during upload ({} of {} bytes sent)", copied, size));
+    }
+    channel.send_eof()
+        .and_then(|_| channel.wait_eof())
+        .and_then(|_| channel.close())
+        .and_then(|_| channel.wait_close())
+        .map_err(|e| format!("Failed to finish SCP upload: {}", e))
+}
+
+pub fn upload_file(session: &Session, local_path: &str, remote_path: &str) -> Result<(), String> {
+    let mut local_file = std::fs::File::open(local_path)
+        .map_err(|e| format!("Failed to open local file: {}", e))?;
+    let size = local_file.metadata()
+        .map_err(|e| format!("Failed to read local file: {}", e))?
+        .len();
+    scp_write(session, remote_path, &mut local_file, size)
+}
+
+pub fn create_empty_file(session: &Session, path: &str) -> Result<(), String> {
+    scp_write(session, path, &mut std::io::empty(), 0)
+}
+
+pub fn download_file(session: &Session, remote_path: &str, local_path: &str) -> Result<(), String> {
+    let (mut channel, stat) = session.scp_recv(Path::new(remote_path))
+        .map_err(|e| format!("Failed to open remote file '{}' over SCP: {}", remote_path, e))?;
+    let mut local_file = std::fs::File::create(local_path)
+        .map_err(|e| format!("Failed to create local file '{}': {}", local_path, e))?;
+
+    let size = stat.size();
+    let copied = std::io::copy(&mut (&mut channel).take(size), &mut local_file)
+        .map_err(|e| format!("Failed to read from remote file: {}", e))?;
+    local_file.flush()
+        .map_err(|e| format!("Failed to write to local file: {}", e))?;
+    let _ = channel.send_eof();
+    let _ = channel.wait_eof();
+    let _ = channel.close();
+    let _ = channel.wait_close();
+
+    if copied != size {
+        return Err(format!("SCP download of '{}' ended after {} of {} bytes", remote_path, copied, size));
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use ssh2::{Channel, Session};
+
+const IDLE_POLL: Duration = Duration::from_millis(2);
+const MAX_IDLE_POLL: Duration = Duration::from_millis(50);
+
+/// How long an idle tunnel sleeps between polls: doubling while nothing moves, so that a quiet
+/// tunnel does not keep a core busy, and back to the shortest as soon as data flows.
+struct IdleBackoff {
+    delay: Duration,
+}
+
+impl IdleBackoff {
+    fn new() -> Self {
+        IdleBackoff { delay: IDLE_POLL }
+    }
+
+    fn reset(&mut self) {
+        self.delay = IDLE_POLL;
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (delay * 2).min(MAX_IDLE_POLL);
+        delay
+    }
+}
+
+fn write_all_nonblocking<W: Write>(writer: &mut W, mut data: &[u8]) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match writer.write(data) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(IDLE_POLL),
+            Err(e) => return Err(e),
+        }
+    }
+    loop {
+        match writer.flush() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(IDLE_POLL),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Turns a `direct-tcpip` channel into a real socket so another `Session` can run its handshake over it.
+/// The channel is bridged to a loopback connection that only this process holds; the jump session is
+/// closed once either end of the tunnel goes away.
+pub fn tunnel_channel(session: Session, mut channel: Channel) -> Result<TcpStream, String> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| format!("Failed to open local tunnel port: {}", e))?;
+    let local_addr = listener.local_addr()
+        .map_err(|e| format!("Failed to open local tunnel port: {}", e))?;
+
+    let client = TcpStream::connect(local_addr)
+        .map_err(|e| format!("Failed to connect to local tunnel: {}", e))?;
+    let (mut server, peer) = listener.accept()
+        .map_err(|e| format!("Failed to accept local tunnel connection: {}", e))?;
+    if Some(peer) != client.local_addr().ok() {
+        return Err("Unexpected connection on local tunnel port".to_string());
+    }
+    server.set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure local tunnel: {}", e))?;
+
+    std::thread::spawn(move || {
+        session.set_blocking(false);
+        let mut buf = [0u8; 32 * 1024];
+        let mut backoff = IdleBackoff::new();
+        loop {
+            let mut idle = true;
+
+            match server.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if write_all_nonblocking(&mut channel, &buf[..n]).is_err() {
+                        break;
+                    }
+                    idle = false;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+
+            match channel.read(&mut buf) {
+                Ok(0) if channel.eof() => break,
+                Ok(0) => {}
+                Ok(n) => {
+                    if write_all_nonblocking(&mut server, &buf[..n]).is_err() {
+                        break;
+                    }
+                    idle = false;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+
+            if idle {
+                std::thread::sleep(backoff.next_delay());
+            } else {
+                backoff.reset();
+            }
+        }
+
+        let _ = server.shutdown(std::net::Shutdown::Both);
+        session.set_blocking(true);
+        session.set_timeout(5000);
+        let _ = channel.close();
+        let _ = session.disconnect(None, "Tunnel closed", None);
+    });
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_polls_back_off() {
+        let mut backoff = IdleBackoff::new();
+        let delays: Vec<u64> = (0..7).map(|_| backoff.next_delay().as_millis() as u64).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 32, 50, 50]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), IDLE_POLL);
+    }
+}
@@ -1,3 +1,5 @@
+use chrono::{Datelike, Utc};
+
 pub fn format_bytes(bytes: u64) -> String {
     const SUFFIXES: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
     if bytes == 0 { return "0 B".to_string(); }
@@ -22,7 +24,22 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
-pub fn parse_ftp_list_line(line: &str) -> Option<(String, u64, bool, String, String)> {
+/// The command a directory listing came from: MLSD, or LIST on servers without it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListFormat {
+    Mlsd,
+    List,
+}
+
+/// Parses one line of MLSD output, or of Unix or DOS style LIST output.
+pub fn parse_ftp_list_line(line: &str, format: ListFormat) -> Option<(String, u64, bool, String, String)> {
+    match format {
+        ListFormat::Mlsd => parse_mlsd_line(line),
+        ListFormat::List => parse_unix_list_line(line).or_else(|| parse_dos_list_line(line)),
+    }
+}
+
+fn parse_mlsd_line(line: &str) -> Option<(String, u64, bool, String, String)> {
     if line.contains('=') && line.contains(';') {
         let size;
         let is_directory;
@@ -82,3 +99,237 @@ pub fn parse_ftp_list_line(line: &str) -> Option<(String, u64, bool, String, Str
 
     None
 }
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+fn month_number(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name)).map(|i| i as u32 + 1)
+}
+
+/// Byte offset of `token`, a slice of `line`, just past its end.
+fn end_of(line: &str, token: &str) -> usize {
+    token.as_ptr() as usize - line.as_ptr() as usize + token.len()
+}
+
+/// Parses a Unix `ls -l` style line, as `ls` and most FTP servers' LIST print it. Some servers leave
+/// out the group or link count, so the date is found by its month name. Dates are `Mon DD HH:MM`
+/// for the past six months and `Mon DD YYYY` otherwise; symlinks are listed under their own name.
+pub fn parse_unix_list_line(line: &str) -> Option<(String, u64, bool, String, String)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let permissions = *tokens.first()?;
+    let mode_chars = permissions.get(1..10)?;
+    if !permissions.starts_with(['-', 'd', 'l', 'c', 'b', 'p', 's'])
+        || !mode_chars.chars().all(|c| "rwxsStTl-".contains(c)) {
+        return None;
+    }
+
+    let month_index = (2..tokens.len().saturating_sub(3)).find(|&i| {
+        month_number(tokens[i]).is_some() && tokens[i - 1].parse::<u64>().is_ok() && tokens[i + 1].parse::<u32>().is_ok()
+    })?;
+    let month = month_number(tokens[month_index])?;
+    let day = tokens[month_index + 1].parse::<u32>().ok()?;
+    let time_or_year = tokens[month_index + 2];
+
+    let date_str = match time_or_year.split_once(':') {
+        Some((hour, minute)) => {
+            let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+            // The year is left out for recent files, so it is the current one unless that would
+            // put the date in the future.
+            let today = Utc::now();
+            let year = if (month, day) > (today.month(), today.day()) { today.year() - 1 } else { today.year() };
+            format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, hour, minute)
+        }
+        None => format!("{}-{:02}-{:02}", time_or_year.parse::<u32>().ok()?, month, day),
+    };
+
+    // A single space separates the date from the name; anything past it belongs to the name.
+    let rest = &line[end_of(line, time_or_year)..];
+    let mut name = rest.strip_prefix(' ').unwrap_or(rest).trim_end_matches(['\r', '\n']);
+    if permissions.starts_with('l') {
+        name = name.split(" -> ").next().unwrap_or(name);
+    }
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    let is_directory = permissions.starts_with('d');
+    // Device files show "major, minor" where the size would be.
+    let size = if is_directory || permissions.starts_with(['c', 'b']) { 0 } else { tokens[month_index - 1].parse().ok()? };
+    Some((name.to_string(), size, is_directory, date_str, permissions.to_string()))
+}
+
+const DOS_DIR_PADDING: &str = "          ";
+
+/// Parses a DOS style line as IIS sends for LIST: `MM-DD-YY  HH:MMAM  <DIR>  name` for directories
+/// and `MM-DD-YYYY  HH:MM  1234  name` for files.
+pub fn parse_dos_list_line(line: &str) -> Option<(String, u64, bool, String, String)> {
+    let mut tokens = line.split_whitespace();
+    let date = tokens.next()?;
+    let time = tokens.next()?;
+    let size_or_dir = tokens.next()?;
+
+    let date_parts: Vec<u32> = date.split(['-', '/']).map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let [month, day, year] = date_parts[..] else { return None };
+    let year = match year {
+        0..=69 => year + 2000,
+        70..=99 => year + 1900,
+        _ => year,
+    };
+
+    let upper_time = time.to_ascii_uppercase();
+    let (clock, meridiem) = match upper_time.strip_suffix("AM").or_else(|| upper_time.strip_suffix("PM")) {
+        Some(clock) => (clock, Some(upper_time.ends_with("PM"))),
+        None => (upper_time.as_str(), None),
+    };
+    let (hour, minute) = clock.split_once(':')?;
+    let (mut hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+    match meridiem {
+        Some(true) if hour < 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let is_directory = size_or_dir.eq_ignore_ascii_case("<DIR>");
+    let size = if is_directory { 0 } else { size_or_dir.replace(',', "").parse::<u64>().ok()? };
+    // IIS pads `<DIR>` to the width of the size column and puts a single space after a size, so
+    // only that padding is dropped and leading spaces in the name survive.
+    let rest = &line[end_of(line, size_or_dir)..];
+    let padding = if is_directory { DOS_DIR_PADDING } else { " " };
+    let name = rest.strip_prefix(padding).unwrap_or_else(|| rest.trim_start()).trim_end_matches(['\r', '\n']);
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    let date_str = format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, hour, minute);
+    Some((name.to_string(), size, is_directory, date_str, "".to_string()))
+}
+
+/// Decodes `%XX` escapes; malformed escapes are kept as-is and invalid UTF-8 is replaced.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Ok(byte) = u8::from_str_radix(value.get(i + 1..i + 3).unwrap_or(""), 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Percent-encodes a URL path, keeping `/` separators and RFC 3986 unreserved characters.
+pub fn percent_encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_symlink() {
+        let (name, size, is_directory, date, permissions) =
+            parse_unix_list_line("lrwxrwxrwx   1 root  root        11 Mar  5  2021 current -> releases/42").unwrap();
+        assert_eq!((name.as_str(), size, is_directory), ("current", 11, false));
+        assert_eq!(date, "2021-03-05");
+        assert_eq!(permissions, "lrwxrwxrwx");
+    }
+
+    #[test]
+    fn unix_time_or_year() {
+        let (_, size, _, date, _) = parse_unix_list_line("-rw-r--r--   1 ftp ftp   1048576 Jan  1 09:05 recent.bin").unwrap();
+        assert_eq!(size, 1048576);
+        assert_eq!(date, format!("{}-01-01 09:05", Utc::now().year()));
+
+        let (_, _, is_directory, date, _) = parse_unix_list_line("drwxr-xr-x   2 ftp ftp      4096 Dec 31  1999 archive").unwrap();
+        assert!(is_directory);
+        assert_eq!(date, "1999-12-31");
+    }
+
+    #[test]
+    fn unix_names_with_spaces() {
+        let name = |line| parse_unix_list_line(line).unwrap().0;
+        assert_eq!(name("-rw-r--r--   1 ftp ftp   12 Jun 10  2020 annual report  final.pdf"), "annual report  final.pdf");
+        assert_eq!(name("-rw-r--r--   1 ftp ftp   12 Jun 10 12:00  leading space.txt"), " leading space.txt");
+        assert_eq!(name("-rw-r--r--   1 ftp ftp   12 Jun 10  2020   two leading\r\n"), "  two leading");
+        // Servers that leave out the group column.
+        assert_eq!(name("-rw-r--r--   1 ftp   12 Jun 10  2020 no group"), "no group");
+    }
+
+    #[test]
+    fn unix_garbage_is_skipped() {
+        for line in [
+            "total 48",
+            "",
+            "drwxr-xr-x   2 ftp ftp      4096 Dec 31  1999 .",
+            "drwxr-xr-x   2 ftp ftp      4096 Dec 31  1999 ..",
+            "-rw-r--r--   1 ftp ftp      4096 Foo 31  1999 bad-month",
+            "hello world this is not a listing line",
+            "01-15-24  03:45PM       <DIR>          aspnet_client",
+        ] {
+            assert!(parse_unix_list_line(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn dos_directory_and_file() {
+        let (name, size, is_directory, date, _) = parse_dos_list_line("01-15-24  03:45PM       <DIR>          aspnet_client").unwrap();
+        assert_eq!((name.as_str(), size, is_directory), ("aspnet_client", 0, true));
+        assert_eq!(date, "2024-01-15 15:45");
+
+        let (name, size, is_directory, date, _) = parse_dos_list_line("02-11-1998  12:05AM              1,234,567 setup log.txt").unwrap();
+        assert_eq!((name.as_str(), size, is_directory), ("setup log.txt", 1234567, false));
+        assert_eq!(date, "1998-02-11 00:05");
+
+        let (_, _, _, date, _) = parse_dos_list_line("12-01-99  12:30PM  42 noon.txt").unwrap();
+        assert_eq!(date, "1999-12-01 12:30");
+    }
+
+    #[test]
+    fn dos_leading_spaces() {
+        assert_eq!(parse_dos_list_line("01-15-24  03:45PM       <DIR>            spaced dir").unwrap().0, "  spaced dir");
+        assert_eq!(parse_dos_list_line("01-15-24  03:45PM                  689  spaced.htm\r\n").unwrap().0, " spaced.htm");
+    }
+
+    #[test]
+    fn dos_garbage_is_skipped() {
+        for line in [
+            "",
+            "Volume in drive C has no label",
+            "01-15-24  03:45PM       <DIR>          .",
+            "13/45/2024  25:99  12 odd",
+            "-rw-r--r--   1 ftp ftp   12 Jun 10  2020 unix.txt",
+        ] {
+            assert!(parse_dos_list_line(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn lines_are_parsed_in_the_listing_format() {
+        let mlsd = "type=file;size=12;modify=20240115154500; notes.txt";
+        let (name, size, is_directory, ..) = parse_ftp_list_line(mlsd, ListFormat::Mlsd).unwrap();
+        assert_eq!((name.as_str(), size, is_directory), ("notes.txt", 12, false));
+        assert!(parse_ftp_list_line(mlsd, ListFormat::List).is_none());
+
+        let unix = "-rw-r--r--   1 ftp ftp   12 Jun 10  2020 a=b;c.txt";
+        assert_eq!(parse_ftp_list_line(unix, ListFormat::List).unwrap().0, "a=b;c.txt");
+        assert!(parse_ftp_list_line("01-15-24  03:45PM       <DIR>          site", ListFormat::List).unwrap().2);
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use aes_gcm::aead::{Aead, AeadCore, OsRng};
+use aes_gcm::aead::rand_core::RngCore;
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Serialize, Deserialize};
+use tauri::{AppHandle, Emitter, Manager};
+use zeroize::{Zeroize, Zeroizing};
+use crate::models::JumpHost;
+
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Clone, Default, Serialize, Deserialize, Zeroize)]
+pub struct VaultEntry {
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub key_passphrase: Option<String>,
+    #[serde(default)]
+    pub proxy_password: Option<String>,
+    #[serde(default)]
+    pub jump_hosts: Vec<JumpHostSecrets>,
+}
+
+impl VaultEntry {
+    pub fn is_empty(&self) -> bool {
+        self.password.is_none() && self.key_passphrase.is_none() && self.proxy_password.is_none() && self.jump_hosts.is_empty()
+    }
+
+    pub fn jump_host(&self, hop: &JumpHost) -> Option<&JumpHostSecrets> {
+        self.jump_hosts.iter().find(|secrets| secrets.matches(hop))
+    }
+}
+
+/// Secrets of one jump host, matched back to its hop by address and user so reordering the
+/// chain keeps them.
+#[derive(Clone, Default, Serialize, Deserialize, Zeroize)]
+pub struct JumpHostSecrets {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub key_passphrase: Option<String>,
+}
+
+impl JumpHostSecrets {
+    pub fn new(hop: &JumpHost, password: Option<String>, key_passphrase: Option<String>) -> Self {
+        JumpHostSecrets {
+            host: hop.host.clone(),
+            port: hop.port,
+            username: hop.username.clone(),
+            password,
+            key_passphrase,
+        }
+    }
+
+    /// Moves the hop's secrets out of it, leaving only what can be written to the site list.
+    pub fn take(hop: &mut JumpHost) -> Self {
+        let (password, key_passphrase) = (hop.password.take(), hop.key_passphrase.take());
+        JumpHostSecrets::new(hop, password, key_passphrase)
+    }
+
+    pub fn matches(&self, hop: &JumpHost) -> bool {
+        self.host == hop.host && self.port == hop.port && self.username == hop.username
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.password.is_none() && self.key_passphrase.is_none()
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct VaultContents {
+    #[serde(default)]
+    entries: HashMap<String, VaultEntry>,
+}
+
+impl Drop for VaultContents {
+    fn drop(&mut self) {
+        self.entries.values_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+}
+
+/// The decrypted vault. The key and secrets are wiped from memory when it is locked or dropped.
+struct UnlockedVault {
+    key: Zeroizing<[u8; 32]>,
+    salt: Vec<u8>,
+    contents: VaultContents,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+pub struct Vault {
+    inner: Mutex<Option<UnlockedVault>>,
+}
+
+fn vault_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app.path().app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))?;
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    Ok(config_dir.join("vault.json"))
+}
+
+fn derive_key(master_password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(master_password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Failed to derive vault key: {}", e))?;
+    Ok(key)
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    STANDARD.decode(value).map_err(|e| format!("Vault file is corrupted ({}): {}", field, e))
+}
+
+fn write_vault(app: &AppHandle, vault: &UnlockedVault) -> Result<(), String> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(&vault.contents)
+        .map_err(|e| format!("Failed to serialize vault: {}", e))?);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(vault.key.as_ref()));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext.as_ref())
+        .map_err(|_| "Failed to encrypt vault".to_string())?;
+
+    let file = VaultFile {
+        version: 1,
+        salt: STANDARD.encode(&vault.salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    };
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize vault: {}", e))?;
+
+    let path = vault_path(app)?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write vault file: {}", e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to write vault file: {}", e))
+}
+
+impl Vault {
+    pub fn status(&self, app: &AppHandle) -> VaultStatus {
+        VaultStatus {
+            exists: self.exists(app),
+            unlocked: self.is_unlocked(app),
+        }
+    }
+
+    pub fn exists(&self, app: &AppHandle) -> bool {
+        vault_path(app).map(|p| p.exists()).unwrap_or(false)
+    }
+
+    pub fn is_unlocked(&self, app: &AppHandle) -> bool {
+        self.lock_if_idle(app);
+        self.inner.lock().unwrap().is_some()
+    }
+
+    /// Unlocks the vault, creating it with this master password if it does not exist yet.
+    pub fn unlock(&self, app: &AppHandle, master_password: &str) -> Result<(), String> {
+        if master_password.is_empty() {
+            return Err("Master password cannot be empty".to_string());
+        }
+
+        let path = vault_path(app)?;
+        let unlocked = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read vault file: {}", e))?;
+            let file: VaultFile = serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse vault file: {}", e))?;
+
+            let salt = decode("salt", &file.salt)?;
+            let nonce = decode("nonce", &file.nonce)?;
+            let ciphertext = decode("ciphertext", &file.ciphertext)?;
+            let key = derive_key(master_password, &salt)?;
+
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()));
+            let plaintext = Zeroizing::new(cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+                .map_err(|_| "Incorrect master password".to_string())?);
+            let contents: VaultContents = serde_json::from_slice(&plaintext)
+                .map_err(|e| format!("Failed to parse vault contents: {}", e))?;
+
+            UnlockedVault { key, salt, contents, last_used: Instant::now() }
+        } else {
+            let mut salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = derive_key(master_password, &salt)?;
+            let vault = UnlockedVault { key, salt, contents: VaultContents::default(), last_used: Instant::now() };
+            write_vault(app, &vault)?;
+            vault
+        };
+
+        *self.inner.lock().unwrap() = Some(unlocked);
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        *self.inner.lock().unwrap() = None;
+    }
+
+    /// Locks the vault if it has not been used within the idle timeout, telling the UI with a
+    /// `vault-locked` event. Returns true if it was locked by this call.
+    pub fn lock_if_idle(&self, app: &AppHandle) -> bool {
+        let locked = self.expire(IDLE_TIMEOUT);
+        if locked {
+            let _ = app.emit("vault-locked", ());
+        }
+        locked
+    }
+
+    /// Locks the vault if it has not been used for `idle`.
+    fn expire(&self, idle: Duration) -> bool {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.as_ref().is_some_and(|v| v.last_used.elapsed() >= idle) {
+            *guard = None;
+            return true;
+        }
+        false
+    }
+
+    pub fn get(&self, app: &AppHandle, site_id: &str) -> Result<Option<VaultEntry>, String> {
+        self.lock_if_idle(app);
+        let mut guard = self.inner.lock().unwrap();
+        let vault = guard.as_mut().ok_or("Credential vault is locked")?;
+        vault.last_used = Instant::now();
+        Ok(vault.contents.entries.get(site_id).cloned())
+    }
+
+    pub fn set(&self, app: &AppHandle, site_id: &str, entry: VaultEntry) -> Result<(), String> {
+        self.lock_if_idle(app);
+        let mut guard = self.inner.lock().unwrap();
+        let vault = guard.as_mut().ok_or("Credential vault is locked")?;
+        vault.last_used = Instant::now();
+        let replaced = if entry.is_empty() {
+            vault.contents.entries.remove(site_id)
+        } else {
+            vault.contents.entries.insert(site_id.to_string(), entry)
+        };
+        if let Some(mut replaced) = replaced {
+            replaced.zeroize();
+        }
+        write_vault(app, vault)
+    }
+
+    pub fn remove(&self, app: &AppHandle, site_id: &str) -> Result<(), String> {
+        self.set(app, site_id, VaultEntry::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_vaults_lock() {
+        let vault = Vault::default();
+        let mut contents = VaultContents::default();
+        contents.entries.insert("site".to_string(), VaultEntry { password: Some("secret".to_string()), ..Default::default() });
+        *vault.inner.lock().unwrap() = Some(UnlockedVault {
+            key: Zeroizing::new([7u8; 32]),
+            salt: vec![0; 16],
+            contents,
+            last_used: Instant::now(),
+        });
+
+        assert!(!vault.expire(Duration::from_secs(60)));
+        assert!(vault.expire(Duration::ZERO));
+        assert!(vault.inner.lock().unwrap().is_none());
+        // Only the call that locks it reports doing so.
+        assert!(!vault.expire(Duration::ZERO));
+    }
+
+    #[test]
+    fn entries_are_wiped() {
+        let mut entry = VaultEntry {
+            password: Some("secret".to_string()),
+            jump_hosts: vec![JumpHostSecrets { host: "jump".to_string(), port: 22, username: "me".to_string(), password: Some("hop".to_string()), key_passphrase: None }],
+            ..Default::default()
+        };
+        entry.zeroize();
+        assert!(entry.is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use std::io::Write;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use crate::http_client::{self, HttpEndpoint, HttpResponse, RequestBody};
+use crate::models::{ConnectionInfo, ConnectionProtocol, FileItem};
+use crate::utils::{format_bytes, percent_decode, percent_encode_path};
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop></d:propfind>"#;
+
+/// A WebDAV server reached over plain HTTP or HTTPS. Every operation is a single request, so the
+/// client holds no connection and is cheap to clone.
+#[derive(Clone)]
+pub struct WebDavClient {
+    endpoint: HttpEndpoint,
+    authorization: Option<String>,
+}
+
+struct DavEntry {
+    path: String,
+    is_directory: bool,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl WebDavClient {
+    /// Builds the client and checks the credentials with a `PROPFIND` on the server root.
+    pub fn connect(conn_info: &ConnectionInfo) -> Result<Self, String> {
+        let authorization = if conn_info.username.is_empty() || conn_info.username == "anonymous" {
+            None
+        } else {
+            let credentials = format!("{}:{}", conn_info.username, conn_info.password);
+            Some(format!("Basic {}", STANDARD.encode(credentials)))
+        };
+        let client = WebDavClient {
+            endpoint: HttpEndpoint {
+                host: conn_info.host.clone(),
+                port: conn_info.port,
+                tls: matches!(conn_info.protocol, ConnectionProtocol::WebDAVS),
+                proxy: conn_info.options.proxy.clone(),
+                connect_timeout: conn_info.options.connect_timeout(),
+                read_timeout: conn_info.options.read_timeout(),
+            },
+            authorization,
+        };
+
+        let response = client.propfind("/", "0")?;
+        client.check_login(&response)?;
+        Ok(client)
+    }
+
+    /// Judges the answer to a PROPFIND of the root made right after connecting.
+    fn check_login(&self, response: &HttpResponse) -> Result<(), String> {
+        match response.status {
+            207 | 200 => Ok(()),
+            // The root itself may be off limits (e.g. Nextcloud serves under /remote.php/dav), which is
+            // still proof that the server answers and accepted the credentials.
+            403 => Ok(()),
+            401 => Err(self.auth_error(response)),
+            404 | 405 => Err(format!("Not a WebDAV endpoint: {}", response.describe())),
+            _ => Err(format!("Server did not respond as a WebDAV server: {}", response.describe())),
+        }
+    }
+
+    fn auth_error(&self, response: &HttpResponse) -> String {
+        let challenge = response.header("WWW-Authenticate").unwrap_or("");
+        if self.authorization.is_some() && !challenge.to_ascii_lowercase().contains("basic") {
+            format!("Server requires an unsupported authentication scheme: {}", challenge)
+        } else {
+            "Authentication failed: invalid username or password".to_string()
+        }
+    }
+
+    fn headers(&self, extra: Vec<(String, String)>) -> Vec<(String, String)> {
+        let mut headers = extra;
+        if let Some(authorization) = &self.authorization {
+            headers.push(("Authorization".to_string(), authorization.clone()));
+        }
+        headers
+    }
+
+    fn request(&self, method: &str, path: &str, extra: Vec<(String, String)>, body: RequestBody) -> Result<HttpResponse, String> {
+        http_client::send(&self.endpoint, method, &percent_encode_path(path), &self.headers(extra), body)
+    }
+
+    fn expect_success(&self, response: HttpResponse, action: &str, path: &str) -> Result<(), String> {
+        match response.status {
+            // A 207 on a write means some members failed; the body lists which.
+            207 => Err(format!("Failed to {} '{}': the server reported a partial failure", action, path)),
+            401 => Err(self.auth_error(&response)),
+            _ if response.is_success() => Ok(()),
+            _ => Err(format!("Failed to {} '{}': {}", action, path, response.describe())),
+        }
+    }
+
+    fn propfind(&self, path: &str, depth: &str) -> Result<HttpResponse, String> {
+        let headers = vec![
+            ("Depth".to_string(), depth.to_string()),
+            ("Content-Type".to_string(), "application/xml; charset=utf-8".to_string()),
+        ];
+        self.request("PROPFIND", path, headers, RequestBody::Bytes(PROPFIND_BODY.as_bytes()))
+    }
+
+    fn collection_path(path: &str) -> String {
+        format!("{}/", path.trim_end_matches('/'))
+    }
+
+    /// Turns an `href` (absolute URL or absolute path, percent-encoded) into a plain remote path.
+    fn href_to_path(&self, href: &str) -> String {
+        let href = href.trim();
+        let path = match href.split_once("://") {
+            Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or("/"),
+            None => href,
+        };
+        let path = percent_decode(path);
+        match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        }
+    }
+
+    fn parse_multistatus(&self, body: &[u8]) -> Result<Vec<DavEntry>, String> {
+        let text = String::from_utf8_lossy(body);
+        let doc = roxmltree::Document::parse(&text)
+            .map_err(|e| format!("Invalid PROPFIND response: {}", e))?;
+        let dav = |node: &roxmltree::Node, name: &str| node.tag_name().name() == name && node.tag_name().namespace() == Some("DAV:");
+
+        let mut entries = Vec::new();
+        for response in doc.descendants().filter(|n| dav(n, "response")) {
+            let Some(href) = response.children().find(|n| dav(n, "href")).and_then(|n| n.text()) else { continue };
+
+            // Only properties from a propstat with a 2xx status are meaningful.
+            let props: Vec<roxmltree::Node> = response.children()
+                .filter(|n| dav(n, "propstat"))
+                .filter(|propstat| propstat.children()
+                    .find(|n| dav(n, "status"))
+                    .and_then(|n| n.text())
+                    .is_some_and(|status| status.split_whitespace().nth(1).is_some_and(|code| code.starts_with('2'))))
+                .flat_map(|propstat| propstat.children().filter(|n| dav(n, "prop")))
+                .flat_map(|prop| prop.children().filter(|n| n.is_element()))
+                .collect();
+            let prop = |name: &str| props.iter().find(|n| dav(n, name));
+
+            let is_directory = prop("resourcetype")
+                .is_some_and(|n| n.children().any(|c| dav(&c, "collection")));
+            let size = prop("getcontentlength")
+                .and_then(|n| n.text())
+                .and_then(|t| t.trim().parse().ok())
+                .unwrap_or(0);
+            let modified = prop("getlastmodified")
+                .and_then(|n| n.text())
+                .and_then(|t| DateTime::parse_from_rfc2822(t.trim()).ok())
+                .map(|dt| dt.with_timezone(&Utc));
+
+            entries.push(DavEntry { path: self.href_to_path(href), is_directory, size, modified });
+        }
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &str) -> Result<DavEntry, String> {
+        let response = self.propfind(path, "0")?;
+        match response.status {
+            207 => self.parse_multistatus(&response.body)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| format!("Server returned no properties for '{}'", path)),
+            401 => Err(self.auth_error(&response)),
+            404 => Err(format!("'{}' does not exist", path)),
+            _ => Err(format!("Failed to access '{}': {}", path, response.describe())),
+        }
+    }
+
+    pub fn list_directory(&self, path: &str) -> Result<Vec<FileItem>, String> {
+        let response = self.propfind(&Self::collection_path(path), "1")?;
+        match response.status {
+            207 => {}
+            401 => return Err(self.auth_error(&response)),
+            404 => return Err(format!("Directory '{}' does not exist", path)),
+            _ => return Err(format!("Failed to list directory '{}': {}", path, response.describe())),
+        }
+
+        let listed = match path.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+        let mut entries = self.parse_multistatus(&response.body)?;
+        if entries.iter().any(|e| e.path == listed && !e.is_directory) {
+            return Err(format!("Path '{}' is not a directory", path));
+        }
+        entries.retain(|e| e.path != listed);
+
+        Ok(entries.into_iter().map(|entry| {
+            let name = entry.path.rsplit('/').next().unwrap_or("").to_string();
+            let size = if entry.is_directory { 0 } else { entry.size };
+            FileItem {
+                name,
+                full_path: entry.path,
+                size,
+                modified: entry.modified,
+                is_directory: entry.is_directory,
+                readable_size: if entry.is_directory { "".to_string() } else { format_bytes(size) },
+                readable_modified: entry.modified.map(|dt| dt.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+                permissions: if entry.is_directory { "drwxr-xr-x".to_string() } else { "-rw-r--r--".to_string() },
+            }
+        }).collect())
+    }
+
+    pub fn create_directory(&self, path: &str) -> Result<(), String> {
+        let response = self.request("MKCOL", &Self::collection_path(path), Vec::new(), RequestBody::Empty)?;
+        if response.status == 405 {
+            return Err(format!("Failed to create directory '{}': it already exists", path));
+        }
+        self.expect_success(response, "create directory", path)
+    }
+
+    /// `DELETE` removes files and whole collections alike.
+    pub fn remove(&self, path: &str) -> Result<(), String> {
+        let target = match self.stat(path) {
+            Ok(entry) if entry.is_directory => Self::collection_path(path),
+            _ => path.to_string(),
+        };
+        let response = self.request("DELETE", &target, Vec::new(), RequestBody::Empty)?;
+        self.expect_success(response, "delete", path)
+    }
+
+    pub fn rename_file(&self, src: &str, dst: &str) -> Result<(), String> {
+        let is_directory = self.stat(src).map(|e| e.is_directory).unwrap_or(false);
+        let (src, dst) = if is_directory {
+            (Self::collection_path(src), Self::collection_path(dst))
+        } else {
+            (src.to_string(), dst.to_string())
+        };
+        let headers = vec![
+            ("Destination".to_string(), format!("{}{}", self.endpoint.origin(), percent_encode_path(&dst))),
+            ("Overwrite".to_string(), "F".to_string()),
+        ];
+        let response = self.request("MOVE", &src, headers, RequestBody::Empty)?;
+        if response.status == 412 {
+            return Err(format!("Failed to rename '{}': '{}' already exists", src, dst));
+        }
+        self.expect_success(response, "rename", &src)
+    }
+
+    pub fn upload_file(&self, local_path: &str, remote_path: &str) -> Result<(), String> {
+        let mut local_file = std::fs::File::open(local_path)
+            .map_err(|e| format!("Failed to open local file: {}", e))?;
+        let len = local_file.metadata()
+            .map_err(|e| format!("Failed to read local file: {}", e))?
+            .len();
+        let response = self.request("PUT", remote_path, Vec::new(), RequestBody::Reader(&mut local_file, len))?;
+        self.expect_success(response, "upload", remote_path)
+    }
+
+    pub fn write_file(&self, remote_path: &str, data: &[u8]) -> Result<(), String> {
+        let response = self.request("PUT", remote_path, Vec::new(), RequestBody::Bytes(data))?;
+        self.expect_success(response, "write", remote_path)
+    }
+
+    fn get_into(&self, remote_path: &str, sink: &mut dyn Write) -> Result<(), String> {
+        let headers = self.headers(Vec::new());
+        let response = http_client::send_to(&self.endpoint, "GET", &percent_encode_path(remote_path), &headers, RequestBody::Empty, sink, false)?;
+        match response.status {
+            404 => Err(format!("Remote file '{}' does not exist", remote_path)),
+            _ => self.expect_success(response, "download", remote_path),
+        }
+    }
+
+    pub fn download_file(&self, remote_path: &str, local_path: &str) -> Result<(), String> {
+        if self.stat(remote_path).is_ok_and(|e| e.is_directory) {
+            return Err(format!("Path '{}' is a directory, not a file", remote_path));
+        }
+        let mut local_file = std::fs::File::create(local_path)
+            .map_err(|e| format!("Failed to create local file '{}': {}", local_path, e))?;
+        let result = self.get_into(remote_path, &mut local_file);
+        if result.is_err() {
+            drop(local_file);
+            let _ = std::fs::remove_file(local_path);
+        }
+        result
+    }
+
+    pub fn read_file(&self, remote_path: &str) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        self.get_into(remote_path, &mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> WebDavClient {
+        WebDavClient {
+            endpoint: HttpEndpoint {
+                host: "cloud.example.com".to_string(),
+                port: 443,
+                tls: true,
+                proxy: None,
+                connect_timeout: std::time::Duration::from_secs(1),
+                read_timeout: std::time::Duration::from_secs(1),
+            },
+            authorization: None,
+        }
+    }
+
+    fn response(status: u16, reason: &str) -> HttpResponse {
+        HttpResponse { status, reason: reason.to_string(), headers: Vec::new(), body: Vec::new() }
+    }
+
+    #[test]
+    fn login_answers() {
+        let client = client();
+        assert!(client.check_login(&response(207, "Multi-Status")).is_ok());
+        assert!(client.check_login(&response(403, "Forbidden")).is_ok());
+        assert_eq!(client.check_login(&response(404, "Not Found")).unwrap_err(), "Not a WebDAV endpoint: HTTP 404 Not Found");
+        assert!(client.check_login(&response(405, "Method Not Allowed")).unwrap_err().starts_with("Not a WebDAV endpoint"));
+        assert!(client.check_login(&response(401, "Unauthorized")).unwrap_err().starts_with("Authentication failed"));
+    }
+
+    /// A Depth: 1 listing as Nextcloud sends it: absolute-URL hrefs, encoded names, and properties
+    /// it cannot report split into a separate 404 propstat.
+    const NEXTCLOUD_LISTING: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>https://cloud.example.com/remote.php/dav/files/alice/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype><d:getlastmodified>Tue, 07 May 2024 10:15:00 GMT</d:getlastmodified></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop><d:getcontentlength/></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://cloud.example.com/remote.php/dav/files/alice/My%20Documents/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop><d:getcontentlength>999</d:getcontentlength></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/alice/r%C3%A9sum%C3%A9.pdf</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype/>
+        <d:getcontentlength>48213</d:getcontentlength>
+        <d:getlastmodified>Mon, 06 May 2024 08:00:00 GMT</d:getlastmodified>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn nextcloud_multistatus() {
+        let entries = client().parse_multistatus(NEXTCLOUD_LISTING.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].path, "/remote.php/dav/files/alice");
+        assert!(entries[0].is_directory);
+        assert!(entries[0].modified.is_some());
+
+        assert_eq!(entries[1].path, "/remote.php/dav/files/alice/My Documents");
+        assert!(entries[1].is_directory);
+        // The length came from a 404 propstat and must be ignored.
+        assert_eq!(entries[1].size, 0);
+
+        assert_eq!(entries[2].path, "/remote.php/dav/files/alice/résumé.pdf");
+        assert!(!entries[2].is_directory);
+        assert_eq!(entries[2].size, 48213);
+        assert_eq!(entries[2].modified.unwrap().to_rfc3339(), "2024-05-06T08:00:00+00:00");
+    }
+
+    #[test]
+    fn invalid_multistatus() {
+        assert!(client().parse_multistatus(b"<html><body>Login").is_err());
+        assert!(client().parse_multistatus(b"<d:multistatus xmlns:d=\"DAV:\"/>").unwrap().is_empty());
+    }
+}
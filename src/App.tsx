@@ -5,6 +5,7 @@ import { TitleBar } from "./components/TitleBar.tsx";
 
 function App() {
   const [view, setView] = useState<"login" | "dashboard">("login");
+  const [sessionId, setSessionId] = useState<string | null>(null);
 
   useEffect(() => {
     const handleContextMenu = (e: MouseEvent) => {
@@ -38,8 +39,8 @@ function App() {
   return (
     <div className="flex flex-col h-screen w-screen pt-8">
       <TitleBar currentPage={view} />
-      {view === "login" && <Login onLoginSuccess={() => setView("dashboard")} />}
-      {view === "dashboard" && <Dashboard onLogout={() => setView("login")} />}
+      {view === "login" && <Login onLoginSuccess={(id) => { setSessionId(id); setView("dashboard"); }} />}
+      {view === "dashboard" && sessionId && <Dashboard sessionId={sessionId} onLogout={() => { setSessionId(null); setView("login"); }} />}
     </div>
   );
 }
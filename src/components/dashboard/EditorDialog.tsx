@@ -7,6 +7,7 @@ import { registerMonacoThemes, getMonacoThemeName } from "../../themes/monaco-th
 import { VideoViewer } from "./VideoViewer";
 
 interface EditorDialogProps {
+    sessionId: string;
     file: FileItem;
     isRemote: boolean;
     onClose: () => void;
@@ -14,7 +15,7 @@ interface EditorDialogProps {
     addToast: (message: string, type: 'info' | 'success' | 'warning' | 'error') => void;
 }
 
-export function EditorDialog({ file, isRemote, onClose, onSaveSuccess, addToast }: EditorDialogProps) {
+export function EditorDialog({ sessionId, file, isRemote, onClose, onSaveSuccess, addToast }: EditorDialogProps) {
     const [content, setContent] = useState<string>("");
     const [imageData, setImageData] = useState<string | null>(null);
     const [loading, setLoading] = useState<boolean>(true);
@@ -61,7 +62,7 @@ export function EditorDialog({ file, isRemote, onClose, onSaveSuccess, addToast
         } else {
             loadContent();
         }
-    }, [file.full_path, isRemote]);
+    }, [sessionId, file.full_path, isRemote]);
 
     useEffect(() => {
         if (isImage(file.name) || isVideo(file.name)) return;
@@ -79,7 +80,7 @@ export function EditorDialog({ file, isRemote, onClose, onSaveSuccess, addToast
     const loadContent = async () => {
         setLoading(true);
         try {
-            const result = await ftp.readTextFile(file.full_path, isRemote);
+            const result = await ftp.readTextFile(sessionId, file.full_path, isRemote);
             if (result.success && result.data !== null) {
                 setContent(result.data);
                 setOriginalContent(result.data);
@@ -98,7 +99,7 @@ export function EditorDialog({ file, isRemote, onClose, onSaveSuccess, addToast
     const loadImage = async () => {
         setLoading(true);
         try {
-            const result = await ftp.readBinaryFile(file.full_path, isRemote);
+            const result = await ftp.readBinaryFile(sessionId, file.full_path, isRemote);
             if (result.success && result.data !== null) {
                 const uint8Array = new Uint8Array(result.data);
                 const blob = new Blob([uint8Array], { type: getMimeType(file.name) });
@@ -129,7 +130,7 @@ export function EditorDialog({ file, isRemote, onClose, onSaveSuccess, addToast
         }
 
         try {
-            const result = await ftp.writeTextFile(file.full_path, currentContent, isRemote);
+            const result = await ftp.writeTextFile(sessionId, file.full_path, currentContent, isRemote);
             if (result.success) {
                 setOriginalContent(currentContent);
                 addToast("Saved", "success");
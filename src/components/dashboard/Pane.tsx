@@ -7,6 +7,7 @@ import { FileItem, ftp } from "../../utils/api";
 import { useState, useMemo, useEffect, useRef } from "react";
 
 interface PaneProps {
+    sessionId: string;
     paneId: 'local' | 'remote';
     isActiveMobile: boolean;
     isActive: boolean;
@@ -37,6 +38,7 @@ interface PaneProps {
 }
 
 export const Pane = ({
+    sessionId,
     paneId,
     isActiveMobile,
     isActive,
@@ -112,7 +114,7 @@ export const Pane = ({
         searchTimeoutRef.current = setTimeout(async () => {
             try {
                 // Всегда рекурсивный поиск
-                const res = await ftp.searchFiles(path, searchQuery, isRemote, true);
+                const res = await ftp.searchFiles(sessionId, path, searchQuery, isRemote, true);
                 if (res.success && res.data) {
                     setSearchResults(res.data);
                 }
@@ -126,7 +128,7 @@ export const Pane = ({
         return () => {
             if (searchTimeoutRef.current) clearTimeout(searchTimeoutRef.current);
         };
-    }, [searchQuery, path, isRemote]);
+    }, [sessionId, searchQuery, path, isRemote]);
 
     const displayFiles = useMemo(() => {
         if (!searchQuery) return files;
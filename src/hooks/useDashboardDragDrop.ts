@@ -11,6 +11,7 @@ interface TransferActions {
 }
 
 export const useDashboardDragDrop = (
+    sessionId: string,
     localPath: string,
     remotePath: string,
     loadLocal: (path: string) => Promise<void>,
@@ -92,7 +93,7 @@ export const useDashboardDragDrop = (
                 const stopSimulation = simulateProgress(id, 3000);
 
                 if (isRemoteSource === isRemoteDest) {
-                    const res = await ftp.renameFile(sourceFullPath, destFullPath, isRemoteSource);
+                    const res = await ftp.renameFile(sessionId, sourceFullPath, destFullPath, isRemoteSource);
                     stopSimulation();
 
                     if (res.success) {
@@ -106,7 +107,7 @@ export const useDashboardDragDrop = (
                     }
                 } else {
                     if (isRemoteSource && !isRemoteDest) {
-                        const res = await ftp.downloadFile(sourceFullPath, destFullPath);
+                        const res = await ftp.downloadFile(sessionId, sourceFullPath, destFullPath);
                         stopSimulation();
 
                         if (res.success) {
@@ -119,7 +120,7 @@ export const useDashboardDragDrop = (
                             addToast(`Failed to download ${fileName}: ${res.error}`, 'error');
                         }
                     } else {
-                        const res = await ftp.uploadFile(sourceFullPath, destFullPath);
+                        const res = await ftp.uploadFile(sessionId, sourceFullPath, destFullPath);
                         stopSimulation();
 
                         if (res.success) {
@@ -161,7 +162,7 @@ export const useDashboardDragDrop = (
 
             await new Promise(resolve => setTimeout(resolve, 500));
 
-            const remoteCheck = await ftp.listRemoteFiles(remotePath);
+            const remoteCheck = await ftp.listRemoteFiles(sessionId, remotePath);
 
             if (isRemoteDest && remoteCheck.success && remoteCheck.data) {
                 const uploadedFileNames = paths.map(p => {
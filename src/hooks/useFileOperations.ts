@@ -2,6 +2,7 @@ import { useState, useCallback } from "react";
 import { ftp, FileItem } from "../utils/api";
 
 export function useFileOperations(
+    sessionId: string,
     localPath: string,
     remotePath: string,
     loadLocal: (path: string) => void,
@@ -46,9 +47,9 @@ export function useFileOperations(
         try {
             let result;
             if (type === 'file') {
-                result = await ftp.createFile(fullPath, isRemote);
+                result = await ftp.createFile(sessionId, fullPath, isRemote);
             } else {
-                result = await ftp.createDirectory(fullPath, isRemote);
+                result = await ftp.createDirectory(sessionId, fullPath, isRemote);
             }
 
             if (result.success) {
@@ -72,7 +73,7 @@ export function useFileOperations(
         } finally {
             if (setLoading) setLoading(false);
         }
-    }, [showCreateDialog, newName, localPath, remotePath, loadLocal, loadRemote, addToast, setLocalLoading, setRemoteLoading]);
+    }, [sessionId, showCreateDialog, newName, localPath, remotePath, loadLocal, loadRemote, addToast, setLocalLoading, setRemoteLoading]);
 
     const handleConfirmRename = useCallback(async () => {
         if (!showRenameDialog || !newName || newName === showRenameDialog.file.name) {
@@ -93,7 +94,7 @@ export function useFileOperations(
         else addToast(`Renaming to ${nameToRename}...`, 'info');
 
         try {
-            const result = await ftp.renameFile(file.full_path, newPath, isRemote);
+            const result = await ftp.renameFile(sessionId, file.full_path, newPath, isRemote);
             if (result.success) {
                 addToast(`Renamed to ${nameToRename}`, 'success');
                 setLastAction({ type: 'rename', oldPath: file.full_path, newPath: newPath, isRemote });
@@ -107,7 +108,7 @@ export function useFileOperations(
         } finally {
             if (setLoading) setLoading(false);
         }
-    }, [showRenameDialog, newName, localPath, remotePath, loadLocal, loadRemote, addToast, setLocalLoading, setRemoteLoading]);
+    }, [sessionId, showRenameDialog, newName, localPath, remotePath, loadLocal, loadRemote, addToast, setLocalLoading, setRemoteLoading]);
 
     const handleConfirmDelete = useCallback(async () => {
         if (!showDeleteDialog) return;
@@ -130,7 +131,7 @@ export function useFileOperations(
                     transferQueue.currentTransferIdRef.current = id;
                     const stopSimulation = transferQueue.simulateProgress(id, 5000);
 
-                    const result = await ftp.deleteFile(file.full_path, isRemote);
+                    const result = await ftp.deleteFile(sessionId, file.full_path, isRemote);
                     stopSimulation();
 
                     if (result.success) {
@@ -142,7 +143,7 @@ export function useFileOperations(
                     }
                     transferQueue.currentTransferIdRef.current = null;
                 } else {
-                    const result = await ftp.deleteFile(file.full_path, isRemote);
+                    const result = await ftp.deleteFile(sessionId, file.full_path, isRemote);
                     if (result.success) {
                         successCount++;
                     } else {
@@ -163,7 +164,7 @@ export function useFileOperations(
         if (errors.length > 0) {
             addToast(`Failed to delete ${errors.length} item(s)`, 'error');
         }
-    }, [showDeleteDialog, localPath, remotePath, loadLocal, loadRemote, addToast, transferQueue]);
+    }, [sessionId, showDeleteDialog, localPath, remotePath, loadLocal, loadRemote, addToast, transferQueue]);
 
     const handleFileAction = useCallback(async (action: 'open' | 'download' | 'delete' | 'rename' | 'properties' | 'move' | 'run' | 'copy_local', file: FileItem, isRemote: boolean) => {
         switch (action) {
@@ -198,7 +199,7 @@ export function useFileOperations(
                         transferQueue.currentTransferIdRef.current = id;
                         const stopSimulation = transferQueue.simulateProgress(id, 3000);
 
-                        const res = await ftp.downloadFile(file.full_path, targetPath);
+                        const res = await ftp.downloadFile(sessionId, file.full_path, targetPath);
                         stopSimulation();
 
                         if (res.success) {
@@ -211,7 +212,7 @@ export function useFileOperations(
                         transferQueue.currentTransferIdRef.current = null;
                     } else {
                         addToast(`Downloading ${file.name}...`, 'info');
-                        const res = await ftp.downloadFile(file.full_path, targetPath);
+                        const res = await ftp.downloadFile(sessionId, file.full_path, targetPath);
                         if (res.success) {
                             addToast("Download complete", 'success');
                             loadLocal(localPath);
@@ -228,7 +229,7 @@ export function useFileOperations(
                         transferQueue.currentTransferIdRef.current = id;
                         const stopSimulation = transferQueue.simulateProgress(id, 3000);
 
-                        const res = await ftp.uploadFile(file.full_path, targetPath);
+                        const res = await ftp.uploadFile(sessionId, file.full_path, targetPath);
                         stopSimulation();
 
                         if (res.success) {
@@ -241,7 +242,7 @@ export function useFileOperations(
                         transferQueue.currentTransferIdRef.current = null;
                     } else {
                         addToast(`Uploading ${file.name}...`, 'info');
-                        const res = await ftp.uploadFile(file.full_path, targetPath);
+                        const res = await ftp.uploadFile(sessionId, file.full_path, targetPath);
                         if (res.success) {
                             addToast("Upload complete", 'success');
                             loadRemote(remotePath);
@@ -255,7 +256,7 @@ export function useFileOperations(
                 if (isRemote) {
                     const targetPath = `${localPath}${localPath.endsWith('\\') ? '' : '\\'}${file.name}`;
                     addToast(`Moving ${file.name} to local...`, 'info');
-                    const res = await ftp.moveFile(file.full_path, targetPath, true);
+                    const res = await ftp.moveFile(sessionId, file.full_path, targetPath, true);
                     if (res.success) {
                         addToast("Move complete", 'success');
                         loadRemote(remotePath);
@@ -266,7 +267,7 @@ export function useFileOperations(
                 } else {
                     const targetPath = `${remotePath}${remotePath.endsWith('/') ? '' : '/'}${file.name}`;
                     addToast(`Moving ${file.name} to remote...`, 'info');
-                    const res = await ftp.moveFile(file.full_path, targetPath, false);
+                    const res = await ftp.moveFile(sessionId, file.full_path, targetPath, false);
                     if (res.success) {
                         addToast("Move complete", 'success');
                         loadLocal(localPath);
@@ -292,11 +293,11 @@ export function useFileOperations(
                 }
                 break;
         }
-    }, [localPath, remotePath, loadLocal, loadRemote, addToast, onFileOpen]);
+    }, [sessionId, localPath, remotePath, loadLocal, loadRemote, addToast, onFileOpen]);
 
     const undoRename = useCallback(async () => {
         if (lastAction && lastAction.type === 'rename') {
-            const res = await ftp.renameFile(lastAction.newPath, lastAction.oldPath, lastAction.isRemote);
+            const res = await ftp.renameFile(sessionId, lastAction.newPath, lastAction.oldPath, lastAction.isRemote);
             if (res.success) {
                 addToast("Undo: Renamed back", 'success');
                 setLastAction(null);
@@ -308,7 +309,7 @@ export function useFileOperations(
         } else {
             addToast("Nothing to undo", 'info');
         }
-    }, [lastAction, localPath, remotePath, loadLocal, loadRemote, addToast]);
+    }, [sessionId, lastAction, localPath, remotePath, loadLocal, loadRemote, addToast]);
 
     return {
         showRenameDialog, setShowRenameDialog,
@@ -1,7 +1,7 @@
 import { useState, useCallback, useEffect, useRef } from "react";
 import { ftp, FileItem, RecentFolder } from "../utils/api";
 
-export function useFileSystem(addToast: (msg: string, type?: any) => void) {
+export function useFileSystem(sessionId: string, addToast: (msg: string, type?: any) => void) {
     const [localPath, setLocalPath] = useState<string>("");
     const [remotePath, setRemotePath] = useState<string>("/");
 
@@ -86,7 +86,7 @@ export function useFileSystem(addToast: (msg: string, type?: any) => void) {
         loadingRemotePath.current = path;
         setRemoteLoading(true);
         try {
-            const res = await ftp.listRemoteFiles(path);
+            const res = await ftp.listRemoteFiles(sessionId, path);
             if (res.success && res.data) {
                 const isRoot = path === "/" || path === "";
                 const filesWithParent = isRoot ? res.data : [
@@ -125,7 +125,7 @@ export function useFileSystem(addToast: (msg: string, type?: any) => void) {
             setRemoteLoading(false);
             loadingRemotePath.current = null;
         }
-    }, [addToast]);
+    }, [sessionId, addToast]);
 
     const navigateLocalUp = useCallback(() => {
         const currentPath = localPathRef.current;
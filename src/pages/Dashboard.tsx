@@ -23,10 +23,11 @@ import { Pane } from "../components/dashboard/Pane";
 import { DragGhost } from "../components/dashboard/DragGhost";
 
 interface DashboardProps {
+    sessionId: string;
     onLogout: () => void;
 }
 
-export function Dashboard({ onLogout }: DashboardProps) {
+export function Dashboard({ sessionId, onLogout }: DashboardProps) {
     const { toasts, addToast } = useToasts();
 
     const {
@@ -40,7 +41,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
         navigateLocalUp, navigateRemoteUp,
         navigateLocalBreadcrumb, navigateRemoteBreadcrumb,
         goBack, goForward
-    } = useFileSystem(addToast);
+    } = useFileSystem(sessionId, addToast);
 
     const [editingFile, setEditingFile] = useState<{
         file: FileItem;
@@ -101,7 +102,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
         handleConfirmCreate,
         handleFileAction,
         undoRename
-    } = useFileOperations(localPath, remotePath, loadLocal, loadRemote, addToast, handleFileOpen, {
+    } = useFileOperations(sessionId, localPath, remotePath, loadLocal, loadRemote, addToast, handleFileOpen, {
         addToQueue,
         startTransfer,
         completeTransfer,
@@ -146,6 +147,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
     );
 
     const { dragState, dragGhostRef, handleMouseDown } = useDashboardDragDrop(
+        sessionId,
         localPath,
         remotePath,
         loadLocal,
@@ -205,7 +207,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
 
     const handleDisconnect = async () => {
         try {
-            await ftp.disconnect();
+            await ftp.disconnect(sessionId);
             onLogout();
         } catch (e) {
         }
@@ -292,6 +294,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
 
             {editingFile && (
                 <EditorDialog
+                    sessionId={sessionId}
                     file={editingFile.file}
                     isRemote={editingFile.isRemote}
                     onClose={() => setEditingFile(null)}
@@ -326,6 +329,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
 
                 <div className="flex-1 flex flex-col lg:flex-row min-w-0">
                     <Pane
+                        sessionId={sessionId}
                         paneId="local"
                         isActiveMobile={mobileActivePane === 'local'}
                         isActive={activePane === 'local'}
@@ -356,6 +360,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
                     />
 
                     <Pane
+                        sessionId={sessionId}
                         paneId="remote"
                         isActiveMobile={mobileActivePane === 'remote'}
                         isActive={activePane === 'remote'}
@@ -4,7 +4,7 @@ import { XCircle, User, Lock, Server } from "lucide-react";
 import background from "../assets/background.jpeg";
 
 interface LoginProps {
-    onLoginSuccess: () => void;
+    onLoginSuccess: (sessionId: string) => void;
 }
 
 interface SavedCredential {
@@ -68,7 +68,7 @@ export function Login({ onLoginSuccess }: LoginProps) {
         try {
             const result = await ftp.connect_auto(host, port, username || undefined, password || undefined);
 
-            if (result.success) {
+            if (result.success && result.data) {
                 if (saveCreds) {
                     const newCred: SavedCredential = {
                         host,
@@ -84,7 +84,7 @@ export function Login({ onLoginSuccess }: LoginProps) {
                     setSavedCreds(updated);
                     localStorage.setItem("bestftp_creds", JSON.stringify(updated));
                 }
-                onLoginSuccess();
+                onLoginSuccess(result.data.session_id);
             } else {
                 setError(result.error || "Connection failed");
             }
@@ -18,6 +18,135 @@ export interface CommandResult<T> {
     error: string | null;
 }
 
+export interface ProxySettings {
+    kind: "socks5" | "http";
+    host: string;
+    port: number;
+    username?: string;
+    password?: string;
+}
+
+export interface JumpHost {
+    host: string;
+    port?: number;
+    username: string;
+    password?: string;
+    private_key_path?: string;
+    key_passphrase?: string;
+    use_agent?: boolean;
+    agent_only?: boolean;
+}
+
+export interface ConnectionOptions {
+    private_key_path?: string;
+    key_passphrase?: string;
+    use_agent?: boolean;
+    agent_only?: boolean;
+    proxy?: ProxySettings;
+    jump_hosts?: JumpHost[];
+    data_mode?: "passive" | "extended-passive" | "active";
+    active_port_range?: { start: number; end: number };
+    connect_timeout_secs?: number;
+    read_timeout_secs?: number;
+    idle_timeout_secs?: number;
+    keepalive_interval_secs?: number;
+    s3_region?: string;
+    s3_use_tls?: boolean;
+    ftp_charset?: string;
+}
+
+export interface SessionLogEntry {
+    session_id: string;
+    timestamp: string;
+    direction: "sent" | "received" | "info" | "error";
+    message: string;
+}
+
+export interface ConnectionStateEvent {
+    session_id: string;
+    state: "connected" | "idle" | "reconnecting" | "lost";
+    message: string | null;
+}
+
+export interface AuthPromptRequest {
+    request_id: string;
+    host: string;
+    username: string;
+    instructions: string;
+    prompts: { text: string; echo: boolean }[];
+}
+
+export interface HostKeyPrompt {
+    request_id: string;
+    host: string;
+    port: number;
+    key_type: string;
+    fingerprint: string;
+}
+
+export interface KnownHostEntry {
+    host: string;
+    key_type: string;
+    fingerprint: string;
+}
+
+export interface ProtocolDetection {
+    protocol: "FTP" | "FTPS" | "FTPSImplicit" | "SFTP";
+    reason: string;
+}
+
+export interface ConnectedSession {
+    session_id: string;
+    message: string;
+    detected: ProtocolDetection | null;
+}
+
+export interface SessionSummary {
+    session_id: string;
+    host: string;
+    port: number;
+    username: string;
+    protocol: string;
+    current_path: string;
+}
+
+export type SiteAuthMethod = "password" | "anonymous" | "private-key" | "agent" | "keyboard-interactive";
+
+export interface SiteProfile {
+    id: string;
+    name: string;
+    group?: string | null;
+    host: string;
+    port: number;
+    username?: string | null;
+    password?: string | null;
+    protocol?: "FTP" | "FTPS" | "FTPSImplicit" | "SFTP" | "WebDAV" | "WebDAVS" | "S3" | null;
+    auth_method: SiteAuthMethod;
+    remote_dir?: string | null;
+    local_dir?: string | null;
+    options?: ConnectionOptions;
+}
+
+export interface SiteConnection extends ConnectedSession {
+    remote_dir: string | null;
+    local_dir: string | null;
+}
+
+export interface ImportIssue {
+    name: string;
+    reason: string;
+}
+
+export interface ImportReport {
+    sites: SiteProfile[];
+    issues: ImportIssue[];
+}
+
+export interface VaultStatus {
+    exists: boolean;
+    unlocked: boolean;
+}
+
 export interface RecentFolder {
     name: string;
     path: string;
@@ -87,23 +216,95 @@ export const isExecutableForEditor = (filename: string) => {
            filename.toLowerCase().includes('.dep');
 };
 
+// Remote-only commands have nothing to fall back on without a session, so they fail before the
+// round trip instead of sending an empty id.
+const withSession = <T>(sessionId: string | null, call: (sessionId: string) => Promise<CommandResult<T>>): Promise<CommandResult<T>> =>
+    sessionId ? call(sessionId) : Promise.resolve({ success: false, data: null, error: "Not connected" });
+
 export const ftp = {
-    connect: (host: string, port: number, username?: string, password?: string, protocol?: string) =>
-        invoke<CommandResult<string>>("connect", { host, port, username, password, protocol }),
+    connect: (host: string, port: number, username?: string, password?: string, protocol?: string, options?: ConnectionOptions) =>
+        invoke<CommandResult<ConnectedSession>>("connect", { host, port, username, password, protocol, options }),
+
+    connect_auto: (host: string, port: number, username?: string, password?: string, options?: ConnectionOptions) =>
+        invoke<CommandResult<ConnectedSession>>("connect_auto", { host, port, username, password, options }),
+
+    connect_url: (url: string, password?: string, options?: ConnectionOptions) =>
+        invoke<CommandResult<SiteConnection>>("connect_url", { url, password, options }),
+
+    disconnect: (sessionId: string) =>
+        invoke<void>("disconnect", { sessionId }),
+
+    listSessions: () => invoke<CommandResult<SessionSummary[]>>("list_sessions"),
+
+    respondAuthPrompt: (requestId: string, responses: string[] | null) =>
+        invoke<CommandResult<void>>("respond_auth_prompt", { requestId, responses }),
+
+    getSessionLog: (sessionId: string) =>
+        invoke<CommandResult<SessionLogEntry[]>>("get_session_log", { sessionId }),
+
+    exportSessionLog: (sessionId: string, path: string) =>
+        invoke<CommandResult<void>>("export_session_log", { sessionId, path }),
+
+    onSessionLog: (callback: (event: { payload: SessionLogEntry }) => void) =>
+        listen<SessionLogEntry>("session-log", callback),
+
+    onAuthPrompt: (callback: (event: { payload: AuthPromptRequest }) => void) =>
+        listen<AuthPromptRequest>("auth-prompt", callback),
+
+    respondHostKeyPrompt: (requestId: string, accept: boolean) =>
+        invoke<CommandResult<void>>("respond_host_key_prompt", { requestId, accept }),
 
-    connect_auto: (host: string, port: number, username?: string, password?: string) =>
-        invoke<CommandResult<string>>("connect_auto", { host, port, username, password }),
+    onHostKeyPrompt: (callback: (event: { payload: HostKeyPrompt }) => void) =>
+        listen<HostKeyPrompt>("host-key-prompt", callback),
 
-    disconnect: () => invoke<void>("disconnect"),
+    listKnownHosts: () => invoke<CommandResult<KnownHostEntry[]>>("list_known_hosts"),
 
-    listRemoteFiles: (path: string) =>
-        invoke<CommandResult<FileItem[]>>("list_remote_files", { path }),
+    removeKnownHost: (host: string) =>
+        invoke<CommandResult<number>>("remove_known_host", { host }),
+
+    listSites: () => invoke<CommandResult<SiteProfile[]>>("list_sites"),
+
+    createSite: (site: SiteProfile) =>
+        invoke<CommandResult<SiteProfile>>("create_site", { site }),
+
+    updateSite: (site: SiteProfile) =>
+        invoke<CommandResult<SiteProfile>>("update_site", { site }),
+
+    deleteSite: (siteId: string) =>
+        invoke<CommandResult<void>>("delete_site", { siteId }),
+
+    listSiteGroups: () => invoke<CommandResult<string[]>>("list_site_groups"),
+
+    setSiteGroup: (siteId: string, group: string | null) =>
+        invoke<CommandResult<void>>("set_site_group", { siteId, group }),
+
+    connectSite: (siteId: string, password?: string) =>
+        invoke<CommandResult<SiteConnection>>("connect_site", { siteId, password }),
+
+    importFileZillaSites: (path?: string) =>
+        invoke<CommandResult<ImportReport>>("import_filezilla_sites", { path }),
+
+    importWinScpSites: (path?: string) =>
+        invoke<CommandResult<ImportReport>>("import_winscp_sites", { path }),
+
+    vaultStatus: () => invoke<CommandResult<VaultStatus>>("vault_status"),
+
+    unlockVault: (masterPassword: string) =>
+        invoke<CommandResult<void>>("unlock_vault", { masterPassword }),
+
+    lockVault: () => invoke<CommandResult<void>>("lock_vault"),
+
+    onVaultLocked: (callback: () => void) =>
+        listen("vault-locked", callback),
+
+    listRemoteFiles: (sessionId: string | null, path: string) =>
+        withSession(sessionId, sessionId => invoke<CommandResult<FileItem[]>>("list_remote_files", { sessionId, path })),
 
     listLocalFiles: (path: string) =>
         invoke<CommandResult<FileItem[]>>("list_local_files", { path }),
 
-    searchFiles: (path: string, query: string, isRemote: boolean, recursive: boolean) =>
-        invoke<CommandResult<FileItem[]>>("search_files", { path, query, isRemote, recursive }),
+    searchFiles: (sessionId: string | null, path: string, query: string, isRemote: boolean, recursive: boolean) =>
+        invoke<CommandResult<FileItem[]>>("search_files", { sessionId, path, query, isRemote, recursive }),
 
     getInitialLocalPath: () => invoke<string>("get_initial_local_path"),
 
@@ -111,38 +312,41 @@ export const ftp = {
 
     getRecentFolders: () => invoke<CommandResult<RecentFolder[]>>("get_recent_folders"),
 
-    deleteFile: (path: string, isRemote: boolean) =>
-        invoke<CommandResult<void>>("delete_file", { path, isRemote }),
+    deleteFile: (sessionId: string | null, path: string, isRemote: boolean) =>
+        invoke<CommandResult<void>>("delete_file", { sessionId, path, isRemote }),
 
-    renameFile: (oldPath: string, newPath: string, isRemote: boolean) =>
-        invoke<CommandResult<void>>("rename_file", { oldPath, newPath, isRemote }),
+    renameFile: (sessionId: string | null, oldPath: string, newPath: string, isRemote: boolean) =>
+        invoke<CommandResult<void>>("rename_file", { sessionId, oldPath, newPath, isRemote }),
 
-    uploadFile: (localPath: string, remotePath: string) =>
-        invoke<CommandResult<void>>("upload_file", { localPath, remotePath }),
+    uploadFile: (sessionId: string | null, localPath: string, remotePath: string) =>
+        withSession(sessionId, sessionId => invoke<CommandResult<void>>("upload_file", { sessionId, localPath, remotePath })),
 
-    downloadFile: (remotePath: string, localPath: string) =>
-        invoke<CommandResult<void>>("download_file", { remotePath, localPath }),
+    downloadFile: (sessionId: string | null, remotePath: string, localPath: string) =>
+        withSession(sessionId, sessionId => invoke<CommandResult<void>>("download_file", { sessionId, remotePath, localPath })),
 
-    moveFile: (sourcePath: string, destPath: string, isRemoteSource: boolean) =>
-        invoke<CommandResult<void>>("move_file", { sourcePath, destPath, isRemoteSource }),
+    moveFile: (sessionId: string | null, sourcePath: string, destPath: string, isRemoteSource: boolean) =>
+        withSession(sessionId, sessionId => invoke<CommandResult<void>>("move_file", { sessionId, sourcePath, destPath, isRemoteSource })),
 
-    createDirectory: (path: string, isRemote: boolean) =>
-        invoke<CommandResult<void>>("create_directory", { path, isRemote }),
+    createDirectory: (sessionId: string | null, path: string, isRemote: boolean) =>
+        invoke<CommandResult<void>>("create_directory", { sessionId, path, isRemote }),
 
-    createFile: (path: string, isRemote: boolean) =>
-        invoke<CommandResult<void>>("create_file", { path, isRemote }),
+    createFile: (sessionId: string | null, path: string, isRemote: boolean) =>
+        invoke<CommandResult<void>>("create_file", { sessionId, path, isRemote }),
 
-    readTextFile: (path: string, isRemote: boolean) =>
-        invoke<CommandResult<string>>("read_text_file", { path, isRemote }),
+    readTextFile: (sessionId: string | null, path: string, isRemote: boolean) =>
+        invoke<CommandResult<string>>("read_text_file", { sessionId, path, isRemote }),
 
-    writeTextFile: (path: string, content: string, isRemote: boolean) =>
-        invoke<CommandResult<void>>("write_text_file", { path, content, isRemote }),
+    writeTextFile: (sessionId: string | null, path: string, content: string, isRemote: boolean) =>
+        invoke<CommandResult<void>>("write_text_file", { sessionId, path, content, isRemote }),
 
-    readBinaryFile: (path: string, isRemote: boolean) =>
-        invoke<CommandResult<number[]>>("read_binary_file", { path, isRemote }),
+    readBinaryFile: (sessionId: string | null, path: string, isRemote: boolean) =>
+        invoke<CommandResult<number[]>>("read_binary_file", { sessionId, path, isRemote }),
 
     runExecutable: (path: string, isRemote: boolean) =>
         invoke<CommandResult<string>>("run_executable", { path, isRemote }),
+    onConnectionState: (callback: (event: { payload: ConnectionStateEvent }) => void) =>
+        listen<ConnectionStateEvent>("connection-state", callback),
+
     onUploadProgress: (callback: (event: { payload: any }) => void) =>
         listen("upload-progress", callback),
 